@@ -0,0 +1,203 @@
+//! Ingest benchmarks. `export` calls `MetricsReceiver::export` in-process from
+//! several tasks at once, so it measures the receiver alone, including the
+//! contention on its shared state (`seen_metrics` and the other maps). `grpc`
+//! starts the dashboard headless and pushes the same exports at it over gRPC
+//! from several concurrent clients.
+//!
+//! Run with `cargo bench --bench ingest`. Each iteration sends one export per
+//! task or client, measured once per concurrency level in `BENCH_CONCURRENCY`
+//! (a comma-separated list, default `1,4,8`). Size the exports with
+//! `BENCH_METRICS` (per request) and `BENCH_POINTS` (per metric).
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use opentelemetry_proto::tonic::collector::metrics::v1::{
+    metrics_service_client::MetricsServiceClient, metrics_service_server::MetricsService,
+    ExportMetricsServiceRequest,
+};
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, KeyValue};
+use opentelemetry_proto::tonic::metrics::v1::{
+    metric::Data, number_data_point::Value, Gauge, Metric, NumberDataPoint, ResourceMetrics,
+    ScopeMetrics,
+};
+use otel_dashboard::metrics::{self, MetricsReceiver, ReceiverOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tonic::transport::Channel;
+use tonic::Request;
+
+fn env_or(name: &str, default: usize) -> usize {
+    std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+fn concurrency_levels() -> Vec<usize> {
+    let levels: Vec<usize> = std::env::var("BENCH_CONCURRENCY")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|level| level.trim().parse().ok())
+        .filter(|&level| level > 0)
+        .collect();
+    if levels.is_empty() {
+        vec![1, 4, 8]
+    } else {
+        levels
+    }
+}
+
+fn synthetic_request(metrics: usize, points: usize) -> ExportMetricsServiceRequest {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+    let metrics = (0..metrics)
+        .map(|m| Metric {
+            name: format!("bench.metric_{}", m),
+            data: Some(Data::Gauge(Gauge {
+                data_points: (0..points)
+                    .map(|p| NumberDataPoint {
+                        attributes: vec![KeyValue {
+                            key: "instance".to_string(),
+                            value: Some(AnyValue {
+                                value: Some(any_value::Value::IntValue(p as i64)),
+                            }),
+                        }],
+                        time_unix_nano: now,
+                        value: Some(Value::AsDouble(p as f64)),
+                        ..Default::default()
+                    })
+                    .collect(),
+            })),
+            ..Default::default()
+        })
+        .collect();
+
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            scope_metrics: vec![ScopeMetrics { metrics, ..Default::default() }],
+            ..Default::default()
+        }],
+    }
+}
+
+/// The dashboard in `--list-duration` mode, which drains the UI channel without
+/// a terminal. It is killed when dropped, so a panicking benchmark doesn't leave
+/// it running for the hour it was started for.
+struct Dashboard {
+    child: Child,
+    port_file: PathBuf,
+}
+
+impl Dashboard {
+    fn start() -> Self {
+        let port_file = std::env::temp_dir().join(format!("otel-dashboard-bench-{}.port", std::process::id()));
+        let _ = std::fs::remove_file(&port_file);
+        let child = Command::new(env!("CARGO_BIN_EXE_otel-dashboard"))
+            .args(["--quiet", "--address", "127.0.0.1:0", "--list-duration", "3600", "--port-file"])
+            .arg(&port_file)
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("failed to start otel-dashboard");
+        Self { child, port_file }
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.port_file);
+    }
+}
+
+fn wait_for_port(port_file: &Path) -> u16 {
+    for _ in 0..100 {
+        if let Some(port) = std::fs::read_to_string(port_file).ok().and_then(|p| p.trim().parse().ok()) {
+            return port;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("otel-dashboard did not write {}", port_file.display());
+}
+
+/// One export from each of `concurrency` tasks at once, straight into the receiver.
+async fn export_in_process(receiver: &Arc<MetricsReceiver>, request: &ExportMetricsServiceRequest, concurrency: usize) {
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let receiver = Arc::clone(receiver);
+            let request = Request::new(request.clone());
+            tokio::spawn(async move { receiver.export(request).await.expect("export failed") })
+        })
+        .collect();
+    for worker in workers {
+        worker.await.expect("worker panicked");
+    }
+}
+
+/// One export from each of `concurrency` clients at once.
+async fn export_concurrently(
+    client: &MetricsServiceClient<Channel>,
+    request: &ExportMetricsServiceRequest,
+    concurrency: usize,
+) {
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let mut client = client.clone();
+            let request = request.clone();
+            tokio::spawn(async move { client.export(request).await.expect("export failed") })
+        })
+        .collect();
+    for worker in workers {
+        worker.await.expect("worker panicked");
+    }
+}
+
+fn export(c: &mut Criterion) {
+    let metrics = env_or("BENCH_METRICS", 10);
+    let points = env_or("BENCH_POINTS", 5);
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start the tokio runtime");
+
+    let (ui_tx, mut ui_rx) = mpsc::unbounded_channel();
+    let (_control_tx, control_rx) = mpsc::unbounded_channel();
+    let receiver = {
+        let _runtime = runtime.enter();
+        metrics::create_receiver(ReceiverOptions::default(), ui_tx, control_rx).expect("failed to create the receiver")
+    };
+    // Stands in for the UI, so the channel doesn't grow for the whole run.
+    runtime.spawn(async move { while ui_rx.recv().await.is_some() {} });
+    let request = synthetic_request(metrics, points);
+
+    let mut group = c.benchmark_group("export");
+    for concurrency in concurrency_levels() {
+        group.throughput(Throughput::Elements((concurrency * metrics * points) as u64));
+        group.bench_function(BenchmarkId::new("tasks", concurrency), |b| {
+            b.to_async(&runtime)
+                .iter(|| export_in_process(&receiver, &request, concurrency))
+        });
+    }
+    group.finish();
+}
+
+fn grpc(c: &mut Criterion) {
+    let metrics = env_or("BENCH_METRICS", 10);
+    let points = env_or("BENCH_POINTS", 5);
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start the tokio runtime");
+
+    let dashboard = Dashboard::start();
+    let port = wait_for_port(&dashboard.port_file);
+    let client = runtime
+        .block_on(MetricsServiceClient::connect(format!("http://127.0.0.1:{}", port)))
+        .expect("failed to connect to otel-dashboard");
+    let request = synthetic_request(metrics, points);
+
+    let mut group = c.benchmark_group("grpc");
+    for concurrency in concurrency_levels() {
+        group.throughput(Throughput::Elements((concurrency * metrics * points) as u64));
+        group.bench_function(BenchmarkId::new("clients", concurrency), |b| {
+            b.to_async(&runtime)
+                .iter(|| export_concurrently(&client, &request, concurrency))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, export, grpc);
+criterion_main!(benches);
@@ -0,0 +1,103 @@
+//! Build metadata for `--version`: the git commit, the build date and the
+//! `opentelemetry-proto` version from `Cargo.lock`. Also compiles the OTLP
+//! metrics protos under `proto/` (copied from `opentelemetry-proto`) into the
+//! file descriptor set `--reflection` serves.
+
+use protobuf::Message;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    Some(text.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+/// `YYYY-MM-DD` in UTC for seconds since the epoch (Howard Hinnant's
+/// `civil_from_days`), so the build script needs no date crate.
+fn utc_date(secs: u64) -> String {
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// The locked version of `package`, read straight from `Cargo.lock`.
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    lines.find(|line| line.trim() == name)?;
+    let version = lines.next()?.trim().strip_prefix("version = \"")?;
+    Some(version.trim_end_matches('"').to_string())
+}
+
+/// The protos behind `MetricsService/Export`, every one listed because the
+/// parser only keeps its inputs in the set, not their imports.
+const PROTOS: [&str; 4] = [
+    "opentelemetry/proto/common/v1/common.proto",
+    "opentelemetry/proto/resource/v1/resource.proto",
+    "opentelemetry/proto/metrics/v1/metrics.proto",
+    "opentelemetry/proto/collector/metrics/v1/metrics_service.proto",
+];
+
+/// Writes the encoded `FileDescriptorSet` of `PROTOS` to `OUT_DIR`, parsed in
+/// pure Rust so building doesn't need `protoc`.
+fn write_descriptor_set() {
+    let proto_dir = Path::new("proto");
+    let descriptors = protobuf_parse::Parser::new()
+        .pure()
+        .include(proto_dir)
+        .inputs(PROTOS.iter().map(|proto| proto_dir.join(proto)))
+        .file_descriptor_set()
+        .expect("the OTLP protos under proto/ should parse");
+    let bytes = descriptors.write_to_bytes().expect("a descriptor set always encodes");
+    let out_dir = std::env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    std::fs::write(Path::new(&out_dir).join("otlp_descriptor_set.bin"), bytes)
+        .expect("OUT_DIR should be writable");
+    println!("cargo:rerun-if-changed=proto");
+}
+
+fn main() {
+    write_descriptor_set();
+
+    let commit = match git(&["rev-parse", "--short", "HEAD"]) {
+        Some(hash) if git(&["status", "--porcelain", "--untracked-files=no"]).is_some() => format!("{}-dirty", hash),
+        Some(hash) => hash,
+        None => "unknown".to_string(),
+    };
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible.
+    let built = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()));
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let proto = locked_version(&lock, "opentelemetry-proto").unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=BUILD_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=BUILD_DATE={}", utc_date(built));
+    println!("cargo:rustc-env=BUILD_OTEL_PROTO_VERSION={}", proto);
+
+    // Rerun when the commit moves or dependencies change, not on every edit.
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    for path in ["HEAD", "index"] {
+        if let Some(path) = git(&["rev-parse", "--git-path", path]) {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    if let Some(head) = git(&["symbolic-ref", "-q", "HEAD"]) {
+        if let Some(path) = git(&["rev-parse", "--git-path", &head]) {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+impl Comparison {
+    fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+        }
+    }
+}
+
+/// A threshold from `--alert "metric_name>100"`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct AlertRule {
+    pub metric: String,
+    pub comparison: Comparison,
+    pub threshold: f64,
+}
+
+impl AlertRule {
+    pub fn is_breached(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::Greater => value > self.threshold,
+            Comparison::GreaterOrEqual => value >= self.threshold,
+            Comparison::Less => value < self.threshold,
+            Comparison::LessOrEqual => value <= self.threshold,
+        }
+    }
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.metric, self.comparison.symbol(), self.threshold)
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let position = rule
+            .find(['>', '<'])
+            .ok_or_else(|| format!("'{}' has no comparison; expected e.g. name>100", rule))?;
+        let (metric, rest) = rule.split_at(position);
+        let (comparison, threshold) = if let Some(t) = rest.strip_prefix(">=") {
+            (Comparison::GreaterOrEqual, t)
+        } else if let Some(t) = rest.strip_prefix("<=") {
+            (Comparison::LessOrEqual, t)
+        } else if let Some(t) = rest.strip_prefix('>') {
+            (Comparison::Greater, t)
+        } else {
+            (Comparison::Less, &rest[1..])
+        };
+
+        let metric = metric.trim();
+        if metric.is_empty() {
+            return Err(format!("'{}' is missing a metric name", rule));
+        }
+        let threshold = threshold
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid threshold in '{}': {}", rule, e))?;
+
+        Ok(Self {
+            metric: metric.to_string(),
+            comparison,
+            threshold,
+        })
+    }
+}
+
+impl TryFrom<String> for AlertRule {
+    type Error = String;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        rule.parse()
+    }
+}
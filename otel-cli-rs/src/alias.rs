@@ -0,0 +1,30 @@
+use crate::rule::{parse_name_value, try_from_str};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// A display name from `--alias "long.metric.name=Short"`. Only the list shows
+/// the alias; data is still stored and matched under the original name.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct MetricAlias {
+    pub metric: String,
+    pub alias: String,
+}
+
+impl FromStr for MetricAlias {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (metric, alias) = parse_name_value(rule, "long.metric.name=Short")?;
+        if alias.is_empty() {
+            return Err(format!("'{}' is missing an alias", rule));
+        }
+
+        Ok(Self {
+            metric: metric.to_string(),
+            alias: alias.to_string(),
+        })
+    }
+}
+
+try_from_str!(MetricAlias);
@@ -0,0 +1,75 @@
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::{mpsc, oneshot};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+enum CaptureCommand {
+    Record(Vec<u8>),
+    Close(oneshot::Sender<()>),
+}
+
+/// Appends raw export requests to a file as length-delimited protobuf, the
+/// same framing `prost::Message::encode_length_delimited` produces. Writes
+/// happen on a background task so the gRPC handler never waits on disk.
+pub struct CaptureWriter {
+    tx: mpsc::UnboundedSender<CaptureCommand>,
+}
+
+impl CaptureWriter {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut writer = BufWriter::new(File::from_std(file));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let path = path.display().to_string();
+
+        tokio::spawn(async move {
+            let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                tokio::select! {
+                    command = rx.recv() => match command {
+                        Some(CaptureCommand::Record(frame)) => {
+                            if let Err(e) = writer.write_all(&frame).await {
+                                tracing::error!("Failed to write capture to {}: {}", path, e);
+                            }
+                        }
+                        Some(CaptureCommand::Close(done)) => {
+                            if let Err(e) = writer.shutdown().await {
+                                tracing::error!("Failed to close capture {}: {}", path, e);
+                            }
+                            let _ = done.send(());
+                            break;
+                        }
+                        None => {
+                            let _ = writer.flush().await;
+                            break;
+                        }
+                    },
+                    _ = flush.tick() => {
+                        if let Err(e) = writer.flush().await {
+                            tracing::error!("Failed to flush capture to {}: {}", path, e);
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self { tx })
+    }
+
+    /// Queues one already length-delimited frame.
+    pub fn record(&self, frame: Vec<u8>) {
+        let _ = self.tx.send(CaptureCommand::Record(frame));
+    }
+
+    /// Flushes everything queued so far and closes the file.
+    pub async fn close(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(CaptureCommand::Close(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+}
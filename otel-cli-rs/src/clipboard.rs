@@ -0,0 +1,19 @@
+/// The system clipboard, opened on first use. It is kept open afterwards
+/// because on X11 and Wayland the copied text is only served while it lives,
+/// or until a clipboard manager takes it over.
+#[derive(Default)]
+pub struct Clipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    /// Puts `text` on the system clipboard. Fails when there is none, e.g.
+    /// over SSH without X forwarding.
+    pub fn copy(&mut self, text: &str) -> Result<(), arboard::Error> {
+        let clipboard = match &mut self.inner {
+            Some(clipboard) => clipboard,
+            None => self.inner.insert(arboard::Clipboard::new()?),
+        };
+        clipboard.set_text(text)
+    }
+}
@@ -0,0 +1,54 @@
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Markdown,
+}
+
+/// A command typed after `:` in the TUI, e.g. `:history 500`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Graph the metric with this name.
+    Select(String),
+    /// Switch to a named color theme.
+    Theme(String),
+    /// Keep this many points per metric.
+    History(usize),
+    Export(ExportFormat),
+}
+
+impl Command {
+    /// One-line summary of the accepted commands, shown for unknown input.
+    pub const USAGE: &'static str =
+        "commands: select <metric>, theme <dark|light>, history <points>, export <csv|md>";
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let (name, argument) = input
+            .split_once(char::is_whitespace)
+            .map_or((input, ""), |(name, argument)| (name, argument.trim()));
+        if argument.is_empty() && !name.is_empty() {
+            return Err(format!("'{}' needs an argument; {}", name, Self::USAGE));
+        }
+
+        match name {
+            "select" => Ok(Command::Select(argument.to_string())),
+            "theme" => Ok(Command::Theme(argument.to_string())),
+            "history" => match argument.parse() {
+                Ok(points) if points > 0 => Ok(Command::History(points)),
+                _ => Err(format!("history needs a positive number of points, got '{}'", argument)),
+            },
+            "export" => match argument {
+                "csv" => Ok(Command::Export(ExportFormat::Csv)),
+                "md" | "markdown" => Ok(Command::Export(ExportFormat::Markdown)),
+                other => Err(format!("unknown export format '{}'; expected csv or md", other)),
+            },
+            other => Err(format!("unknown command '{}'; {}", other, Self::USAGE)),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::alert::AlertRule;
+use crate::alias::MetricAlias;
+use crate::retention::HistoryOverride;
+use crate::threshold::Threshold;
+use crate::error::DashboardError;
+use crate::json::OutputFormat;
+use crate::ui::{DefaultView, ThemeChoice, Timezone};
+use serde::{Deserialize, Deserializer};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Settings read from `--config`. Every key mirrors a command-line flag, and a
+/// flag given on the command line always wins over the value from the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub address: Option<Vec<SocketAddr>>,
+    pub http_address: Option<SocketAddr>,
+    pub debug: Option<bool>,
+    pub quiet: Option<bool>,
+    pub log_file: Option<PathBuf>,
+    pub cumulative_to_delta: Option<bool>,
+    pub include: Option<String>,
+    pub exclude: Option<String>,
+    pub self_metrics: Option<String>,
+    pub self_metrics_interval: Option<u64>,
+    pub uds: Option<PathBuf>,
+    pub no_health: Option<bool>,
+    pub reflection: Option<bool>,
+    pub port_file: Option<PathBuf>,
+    pub capture: Option<PathBuf>,
+    pub forward: Option<String>,
+    pub influx_out: Option<String>,
+    pub alert: Option<Vec<AlertRule>>,
+    pub alias: Option<Vec<MetricAlias>>,
+    pub threshold: Option<Vec<Threshold>>,
+    pub normalize: Option<bool>,
+    pub compact: Option<bool>,
+    pub split: Option<u16>,
+    pub no_sort: Option<bool>,
+    pub restore_session: Option<bool>,
+    pub watch: Option<u64>,
+    pub format: Option<OutputFormat>,
+    pub show_source: Option<bool>,
+    pub flatten_attributes: Option<bool>,
+    pub history: Option<usize>,
+    pub history_for: Option<Vec<HistoryOverride>>,
+    pub max_series: Option<usize>,
+    pub stale_timeout: Option<u64>,
+    pub update_window: Option<u64>,
+    pub cardinality_warn: Option<usize>,
+    pub max_per_frame: Option<u64>,
+    pub dedup: Option<f64>,
+    pub dedup_batch: Option<bool>,
+    pub realtime: Option<bool>,
+    pub precision: Option<u8>,
+    pub timezone: Option<Timezone>,
+    pub theme: Option<ThemeChoice>,
+    pub default_view: Option<DefaultView>,
+    pub max_recv_size: Option<usize>,
+    pub rate_limit: Option<u64>,
+    pub fail_rate: Option<f64>,
+    pub clear_updates_on_select: Option<bool>,
+}
+
+/// `address` takes one address, or a list like repeated `--address`.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<SocketAddr>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged, expecting = "an address like \"127.0.0.1:4317\", or a list of them")]
+    enum Addresses {
+        One(SocketAddr),
+        Many(Vec<SocketAddr>),
+    }
+    Ok(Some(match Addresses::deserialize(deserializer)? {
+        Addresses::One(address) => vec![address],
+        Addresses::Many(addresses) => addresses,
+    }))
+}
+
+pub fn load(path: &Path) -> Result<FileConfig, DashboardError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        DashboardError::Config(format!("cannot read {}: {}", path.display(), e))
+    })?;
+
+    toml::from_str(&contents).map_err(|e| {
+        DashboardError::Config(format!(
+            "cannot parse {}: {}\n\
+             note: command-line flags take precedence over this file, but every key in it must still be valid",
+            path.display(),
+            e.to_string().trim_end()
+        ))
+    })
+}
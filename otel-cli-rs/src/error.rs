@@ -4,11 +4,20 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum DashboardError {
     #[error("Failed to start server: {0}")]
-    ServerError(#[from] tonic::transport::Error),
+    Server(#[from] tonic::transport::Error),
 
     #[error("IO error: {0}")]
-    IoError(#[from] io::Error),
+    Io(#[from] io::Error),
 
     #[error("Channel error: {0}")]
-    ChannelError(String),
+    Channel(String),
+
+    #[error("Invalid metric name pattern: {0}")]
+    Pattern(#[from] regex::Error),
+
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    #[error("Self-test failed: {0}")]
+    SelfTest(String),
 }
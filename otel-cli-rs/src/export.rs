@@ -0,0 +1,60 @@
+use crate::metrics::MetricPoint;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Replaces characters that are awkward in file names (path separators, spaces, ...).
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect()
+}
+
+fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Writes `timestamp,value` rows to `<metric>-<epoch>.csv` in the working directory.
+pub fn write_csv<'a>(
+    metric_name: &str,
+    points: impl IntoIterator<Item = &'a MetricPoint>,
+) -> io::Result<PathBuf> {
+    let path = PathBuf::from(format!("{}-{}.csv", sanitize_file_name(metric_name), epoch_secs()));
+    let mut writer = BufWriter::new(File::create(&path)?);
+
+    writeln!(writer, "timestamp,value")?;
+    for point in points {
+        writeln!(writer, "{},{}", point.timestamp, point.value)?;
+    }
+    writer.flush()?;
+
+    Ok(path)
+}
+
+/// Escapes the characters that would break a Markdown table cell.
+fn markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// Writes a Markdown document with a `title` heading and one table to
+/// `otel-dashboard-<epoch>.md` in the working directory.
+pub fn write_markdown_report(title: &str, header: &[&str], rows: &[Vec<String>]) -> io::Result<PathBuf> {
+    let path = PathBuf::from(format!("otel-dashboard-{}.md", epoch_secs()));
+    let mut writer = BufWriter::new(File::create(&path)?);
+
+    writeln!(writer, "# {}", title)?;
+    writeln!(writer)?;
+    writeln!(writer, "| {} |", header.join(" | "))?;
+    writeln!(writer, "|{}", "---|".repeat(header.len()))?;
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|cell| markdown_cell(cell)).collect();
+        writeln!(writer, "| {} |", cells.join(" | "))?;
+    }
+    writer.flush()?;
+
+    Ok(path)
+}
@@ -0,0 +1,61 @@
+use crate::error::DashboardError;
+use opentelemetry_proto::tonic::collector::metrics::v1::{
+    metrics_service_client::MetricsServiceClient, ExportMetricsServiceRequest,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::mpsc;
+use tonic::transport::Endpoint;
+
+/// Requests waiting for the downstream collector before new ones are dropped.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Re-exports every received request to a downstream collector for `--forward`.
+/// Sends happen on a background task behind a bounded queue, so a slow or
+/// unreachable collector costs forwarded requests, never local display.
+pub struct Forwarder {
+    tx: mpsc::Sender<ExportMetricsServiceRequest>,
+    dropped: AtomicU64,
+}
+
+impl Forwarder {
+    pub fn start(endpoint: &str) -> Result<Self, DashboardError> {
+        let channel = Endpoint::from_shared(endpoint.to_string())
+            .map_err(|e| DashboardError::Config(format!("invalid forward endpoint '{}': {}", endpoint, e)))?
+            .connect_lazy();
+        let mut client = MetricsServiceClient::new(channel);
+        let (tx, mut rx) = mpsc::channel(QUEUE_CAPACITY);
+        let endpoint = endpoint.to_string();
+
+        tokio::spawn(async move {
+            // Only changes between healthy and failing are logged, so a down
+            // collector doesn't produce a warning per request.
+            let mut healthy = true;
+            while let Some(request) = rx.recv().await {
+                match client.export(request).await {
+                    Ok(_) if !healthy => {
+                        tracing::info!("Forwarding to {} recovered", endpoint);
+                        healthy = true;
+                    }
+                    Ok(_) => {}
+                    Err(status) if healthy => {
+                        tracing::warn!("Forwarding to {} failed: {}", endpoint, status);
+                        healthy = false;
+                    }
+                    Err(_) => {}
+                }
+            }
+        });
+
+        Ok(Self { tx, dropped: AtomicU64::new(0) })
+    }
+
+    /// Queues a copy of `request` for the downstream collector.
+    pub fn forward(&self, request: ExportMetricsServiceRequest) {
+        if self.tx.try_send(request).is_err() {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped.is_power_of_two() {
+                tracing::warn!("Forward queue full; {} request(s) not forwarded so far", dropped);
+            }
+        }
+    }
+}
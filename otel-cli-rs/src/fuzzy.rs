@@ -0,0 +1,83 @@
+/// Bonus for a character matched right after the previous match.
+const CONSECUTIVE_BONUS: i64 = 5;
+/// Bonus for a character that starts a name segment.
+const SEGMENT_START_BONUS: i64 = 3;
+/// Cap on the penalty for the characters skipped between two matches.
+const MAX_GAP_PENALTY: usize = 3;
+
+/// Scores `candidate` for the metric finder, fzf-style: every character of
+/// `query` must appear in it in order, ignoring case. Runs of consecutive
+/// characters and matches at the start of a segment (after `.`, `_`, `-`, `/`
+/// or a space) rank higher; gaps and longer names rank lower. `None` when the
+/// query isn't a subsequence of the candidate.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut next = 0;
+    let mut score = 0;
+    let mut last_match: Option<usize> = None;
+    for (i, &c) in candidate.iter().enumerate() {
+        if next == query.len() {
+            break;
+        }
+        if c != query[next] {
+            continue;
+        }
+        score += 1;
+        let gap = last_match.map_or(i, |last| i - last - 1);
+        if gap == 0 && last_match.is_some() {
+            score += CONSECUTIVE_BONUS;
+        } else {
+            score -= gap.min(MAX_GAP_PENALTY) as i64;
+        }
+        if i == 0 || matches!(candidate[i - 1], '.' | '_' | '-' | '/' | ' ') {
+            score += SEGMENT_START_BONUS;
+        }
+        last_match = Some(i);
+        next += 1;
+    }
+    (next == query.len()).then(|| score - (candidate.len() / 8) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_matches_score_none() {
+        assert_eq!(score("xyz", "http.request.duration"), None);
+        // Characters must appear in order.
+        assert!(score("dr", "http.request.duration").is_some());
+        assert_eq!(score("rdh", "http.request.duration"), None);
+        assert_eq!(score("httpp", "http"), None);
+    }
+
+    #[test]
+    fn consecutive_matches_rank_higher() {
+        // Same length, so only the spacing of the matches differs.
+        assert!(score("abc", "abcxxxxx") > score("abc", "axbxcxxx"));
+        assert!(score("req", "xrequest") > score("req", "rxexqxxx"));
+    }
+
+    #[test]
+    fn segment_starts_rank_higher() {
+        assert!(score("hrd", "http.request.duration") > score("hrd", "httpxrequestxduration"));
+        for separator in ['.', '_', '-', '/', ' '] {
+            let segmented = format!("ab{}cd", separator);
+            assert!(score("c", &segmented) > score("c", "abxcd"), "{:?}", separator);
+        }
+    }
+
+    #[test]
+    fn longer_names_rank_lower_and_case_is_ignored() {
+        assert!(score("cpu", "cpu.usage") > score("cpu", "cpu.usage.per.logical.core"));
+        assert_eq!(score("HRD", "http.request.duration"), score("hrd", "HTTP.Request.Duration"));
+    }
+
+    #[test]
+    fn ranking_prefers_the_tightest_match() {
+        let mut names = ["http.server.response.duration", "hydrated", "http.request.duration"];
+        names.sort_by_key(|name| std::cmp::Reverse(score("hrd", name)));
+        assert_eq!(names[0], "http.request.duration");
+    }
+}
@@ -0,0 +1,177 @@
+use crate::metrics::MetricsReceiver;
+use crate::otlp_json;
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, DefaultBodyLimit, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use opentelemetry_proto::tonic::collector::metrics::v1::{
+    metrics_service_server::MetricsService, ExportMetricsServiceRequest,
+    ExportMetricsServiceResponse,
+};
+use prost::Message;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::transport::server::TcpConnectInfo;
+use tonic::{Code, Request};
+
+const JSON: &str = "application/json";
+const PROTOBUF: &str = "application/x-protobuf";
+
+enum Encoding {
+    Json,
+    Protobuf,
+}
+
+fn request_encoding(headers: &HeaderMap) -> Option<Encoding> {
+    let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    match mime {
+        JSON => Some(Encoding::Json),
+        PROTOBUF => Some(Encoding::Protobuf),
+        _ => None,
+    }
+}
+
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::InvalidArgument => StatusCode::BAD_REQUEST,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// `POST /v1/metrics`: decodes the body according to its content type and hands
+/// it to the same `export` the gRPC service uses.
+async fn export_metrics(
+    State(receiver): State<Arc<MetricsReceiver>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(encoding) = request_encoding(&headers) else {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("expected Content-Type {} or {}", JSON, PROTOBUF),
+        )
+            .into_response();
+    };
+
+    let decoded = match encoding {
+        Encoding::Json => otlp_json::decode_request(&body),
+        Encoding::Protobuf => ExportMetricsServiceRequest::decode(body).map_err(|e| e.to_string()),
+    };
+    let request = match decoded {
+        Ok(request) => request,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    // Same extension tonic attaches to gRPC requests, so `remote_addr()` works for both.
+    let mut request = Request::new(request);
+    request.extensions_mut().insert(TcpConnectInfo {
+        local_addr: None,
+        remote_addr: Some(peer),
+    });
+
+    let response = match receiver.export(request).await {
+        Ok(response) => response.into_inner(),
+        Err(status) => return (http_status(status.code()), status.message().to_string()).into_response(),
+    };
+
+    match encoding {
+        Encoding::Json => (
+            [(header::CONTENT_TYPE, JSON)],
+            otlp_json::encode_response(&response),
+        )
+            .into_response(),
+        Encoding::Protobuf => (
+            [(header::CONTENT_TYPE, PROTOBUF)],
+            ExportMetricsServiceResponse::encode_to_vec(&response),
+        )
+            .into_response(),
+    }
+}
+
+/// Serves OTLP/HTTP on `addr` until the server fails. `max_body_size` replaces
+/// axum's 2MB default body limit.
+pub async fn serve(addr: SocketAddr, receiver: Arc<MetricsReceiver>, max_body_size: Option<usize>) {
+    let mut app = Router::new()
+        .route("/v1/metrics", post(export_metrics))
+        .with_state(receiver);
+    if let Some(limit) = max_body_size {
+        app = app.layer(DefaultBodyLimit::max(limit));
+    }
+
+    tracing::info!("Starting OTLP/HTTP receiver on {}", addr);
+    if let Err(e) = axum::Server::bind(&addr).serve(app.into_make_service_with_connect_info::<SocketAddr>()).await {
+        tracing::error!("OTLP/HTTP receiver on {} failed: {}", addr, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{ReceiverOptions, UiMessage};
+    use crate::testutil;
+    use opentelemetry_proto::tonic::metrics::v1::{metric::Data, number_data_point::Value};
+
+    async fn post_json(receiver: Arc<MetricsReceiver>, body: &'static str) -> StatusCode {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, JSON.parse().unwrap());
+        let peer = "127.0.0.1:4318".parse().unwrap();
+        export_metrics(State(receiver), ConnectInfo(peer), headers, Bytes::from(body))
+            .await
+            .status()
+    }
+
+    #[tokio::test]
+    async fn json_example_from_the_otlp_spec_is_accepted() {
+        let (receiver, mut rx) = testutil::in_memory_receiver(ReceiverOptions::default()).unwrap();
+        let status = post_json(receiver.clone(), include_str!("../tests/fixtures/otlp_metrics.json")).await;
+        receiver.shutdown().await;
+        assert_eq!(status, StatusCode::OK);
+
+        let mut points = Vec::new();
+        while let Ok(message) = rx.try_recv() {
+            if let UiMessage::MetricDataPointBatch(batch) = message {
+                points.extend(batch.into_iter().map(|(name, point)| (name, point.value)));
+            }
+        }
+        assert!(points.contains(&("my.counter".to_string(), 5.0)), "{:?}", points);
+        assert!(points.contains(&("my.gauge".to_string(), 10.0)), "{:?}", points);
+        assert!(points.iter().any(|(name, _)| name.starts_with("my.histogram")), "{:?}", points);
+    }
+
+    #[test]
+    fn json_int64_may_be_a_string_or_a_number() {
+        let body = br#"{"resourceMetrics":[{"scopeMetrics":[{"metrics":[{"name":"m","gauge":{"dataPoints":[
+            {"asInt":"7","timeUnixNano":"1700000000000000000"},
+            {"asInt":8,"time_unix_nano":1700000000000000001}
+        ]}}]}]}]}"#;
+        let request = otlp_json::decode_request(body).unwrap();
+        let metric = &request.resource_metrics[0].scope_metrics[0].metrics[0];
+        let Some(Data::Gauge(gauge)) = &metric.data else {
+            panic!("not a gauge: {:?}", metric.data);
+        };
+        let points: Vec<_> = gauge.data_points.iter().map(|p| (p.value.clone(), p.time_unix_nano)).collect();
+        assert_eq!(
+            points,
+            vec![
+                (Some(Value::AsInt(7)), 1_700_000_000_000_000_000),
+                (Some(Value::AsInt(8)), 1_700_000_000_000_000_001),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_a_bad_request() {
+        let (receiver, _rx) = testutil::in_memory_receiver(ReceiverOptions::default()).unwrap();
+        let status = post_json(receiver.clone(), r#"{"resourceMetrics":[{"scopeMetrics":"nope"}]}"#).await;
+        receiver.shutdown().await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
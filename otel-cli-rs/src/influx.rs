@@ -0,0 +1,362 @@
+use crate::error::DashboardError;
+use crate::metrics::{format_any_value, quantile_label};
+use opentelemetry_proto::tonic::common::v1::KeyValue;
+use opentelemetry_proto::tonic::metrics::v1::{
+    number_data_point, DataPointFlags, ExponentialHistogramDataPoint, HistogramDataPoint, NumberDataPoint,
+    SummaryDataPoint,
+};
+use std::fs::OpenOptions;
+use std::net::UdpSocket as StdUdpSocket;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::net::UdpSocket;
+use tokio::sync::{mpsc, oneshot};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Largest UDP payload sent, so datagrams fit a typical MTU unfragmented.
+const MAX_DATAGRAM: usize = 1400;
+
+enum InfluxCommand {
+    Write(Vec<String>),
+    Close(oneshot::Sender<()>),
+}
+
+enum Sink {
+    File(BufWriter<File>),
+    Udp(UdpSocket),
+}
+
+impl Sink {
+    async fn write(&mut self, lines: &[String]) -> std::io::Result<()> {
+        match self {
+            Sink::File(writer) => {
+                for line in lines {
+                    writer.write_all(line.as_bytes()).await?;
+                    writer.write_all(b"\n").await?;
+                }
+                Ok(())
+            }
+            Sink::Udp(socket) => {
+                for datagram in datagrams(lines) {
+                    socket.send(datagram.as_bytes()).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Sink::File(writer) => writer.flush().await,
+            Sink::Udp(_) => Ok(()),
+        }
+    }
+
+    async fn close(&mut self) -> std::io::Result<()> {
+        match self {
+            Sink::File(writer) => writer.shutdown().await,
+            Sink::Udp(_) => Ok(()),
+        }
+    }
+}
+
+/// Packs newline-terminated lines into datagrams of at most `MAX_DATAGRAM`
+/// bytes. Lines never span datagrams; one longer than the limit goes alone.
+fn datagrams(lines: &[String]) -> Vec<String> {
+    let mut datagrams = Vec::new();
+    let mut datagram = String::new();
+    for line in lines {
+        if !datagram.is_empty() && datagram.len() + line.len() + 1 > MAX_DATAGRAM {
+            datagrams.push(std::mem::take(&mut datagram));
+        }
+        datagram.push_str(line);
+        datagram.push('\n');
+    }
+    if !datagram.is_empty() {
+        datagrams.push(datagram);
+    }
+    datagrams
+}
+
+/// Writes every received data point in InfluxDB line protocol for
+/// `--influx-out`, either appended to a file or sent to a `udp://host:port`
+/// listener. Like `--capture`, writes happen on a background task.
+pub struct InfluxWriter {
+    tx: mpsc::UnboundedSender<InfluxCommand>,
+}
+
+impl InfluxWriter {
+    pub fn open(target: &str) -> Result<Self, DashboardError> {
+        let mut sink = match target.strip_prefix("udp://") {
+            Some(endpoint) => {
+                let bind = if endpoint.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" };
+                let socket = StdUdpSocket::bind(bind)?;
+                socket.connect(endpoint).map_err(|e| {
+                    DashboardError::Config(format!("invalid influx endpoint '{}': {}", target, e))
+                })?;
+                socket.set_nonblocking(true)?;
+                Sink::Udp(UdpSocket::from_std(socket)?)
+            }
+            None => {
+                let file = OpenOptions::new().create(true).append(true).open(target)?;
+                Sink::File(BufWriter::new(File::from_std(file)))
+            }
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let target = target.to_string();
+
+        tokio::spawn(async move {
+            let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                tokio::select! {
+                    command = rx.recv() => match command {
+                        Some(InfluxCommand::Write(lines)) => {
+                            if let Err(e) = sink.write(&lines).await {
+                                tracing::error!("Failed to write line protocol to {}: {}", target, e);
+                            }
+                        }
+                        Some(InfluxCommand::Close(done)) => {
+                            if let Err(e) = sink.close().await {
+                                tracing::error!("Failed to close {}: {}", target, e);
+                            }
+                            let _ = done.send(());
+                            break;
+                        }
+                        None => {
+                            let _ = sink.flush().await;
+                            break;
+                        }
+                    },
+                    _ = flush.tick() => {
+                        if let Err(e) = sink.flush().await {
+                            tracing::error!("Failed to flush line protocol to {}: {}", target, e);
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self { tx })
+    }
+
+    /// Queues the lines of one export request.
+    pub fn write(&self, lines: Vec<String>) {
+        if !lines.is_empty() {
+            let _ = self.tx.send(InfluxCommand::Write(lines));
+        }
+    }
+
+    /// Flushes everything queued so far and closes the file.
+    pub async fn close(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(InfluxCommand::Close(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+}
+
+/// Escapes `,`, ` ` and, for tags and field keys, `=` as line protocol requires.
+/// Newlines can't be escaped at all, so they become spaces.
+fn escape(text: &str, equals: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ',' | ' ' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '=' if equals => escaped.push_str("\\="),
+            '\n' | '\r' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `name,tag=value,...` with the tags sorted by key, as InfluxDB prefers.
+/// Empty values are left out since line protocol can't express them.
+fn series(name: &str, attributes: &[KeyValue]) -> String {
+    let mut tags: Vec<(String, String)> = attributes
+        .iter()
+        .map(|kv| (kv.key.clone(), kv.value.as_ref().map(format_any_value).unwrap_or_default()))
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        .collect();
+    tags.sort();
+    let mut series = escape(name, false);
+    for (key, value) in tags {
+        series.push_str(&format!(",{}={}", escape(&key, true), escape(&value, true)));
+    }
+    series
+}
+
+fn float_field(key: &str, value: f64) -> Option<String> {
+    // NaN and infinities are rejected by InfluxDB, so the field is dropped.
+    value.is_finite().then(|| format!("{}={}", escape(key, true), value))
+}
+
+fn push_line(lines: &mut Vec<String>, series: String, fields: Vec<String>, time_unix_nano: u64) {
+    if fields.is_empty() {
+        return;
+    }
+    let mut line = format!("{} {}", series, fields.join(","));
+    // Without a timestamp the server uses its own receive time.
+    if time_unix_nano > 0 {
+        line.push_str(&format!(" {}", time_unix_nano));
+    }
+    lines.push(line);
+}
+
+fn no_recorded_value(flags: u32) -> bool {
+    flags & DataPointFlags::NoRecordedValueMask as u32 != 0
+}
+
+// One line per data point, measured as the metric name with the point's
+// attributes as tags. Gauges and sums have a `value` field, histograms `count`
+// and `sum`, and summaries also one field per quantile. The receiver calls
+// these after its own filtering, so `--dedup-batch` applies here too.
+
+/// The line of a gauge or sum point.
+pub fn number_point(name: &str, point: &NumberDataPoint, lines: &mut Vec<String>) {
+    if no_recorded_value(point.flags) {
+        return;
+    }
+    let field = match point.value {
+        Some(number_data_point::Value::AsDouble(v)) => float_field("value", v),
+        Some(number_data_point::Value::AsInt(v)) => Some(format!("value={}i", v)),
+        None => None,
+    };
+    push_line(lines, series(name, &point.attributes), field.into_iter().collect(), point.time_unix_nano);
+}
+
+pub fn histogram_point(name: &str, point: &HistogramDataPoint, lines: &mut Vec<String>) {
+    if no_recorded_value(point.flags) {
+        return;
+    }
+    let mut fields = vec![format!("count={}i", point.count)];
+    fields.extend(point.sum.and_then(|sum| float_field("sum", sum)));
+    push_line(lines, series(name, &point.attributes), fields, point.time_unix_nano);
+}
+
+pub fn exponential_histogram_point(name: &str, point: &ExponentialHistogramDataPoint, lines: &mut Vec<String>) {
+    if no_recorded_value(point.flags) {
+        return;
+    }
+    let mut fields = vec![format!("count={}i", point.count)];
+    fields.extend(point.sum.and_then(|sum| float_field("sum", sum)));
+    push_line(lines, series(name, &point.attributes), fields, point.time_unix_nano);
+}
+
+pub fn summary_point(name: &str, point: &SummaryDataPoint, lines: &mut Vec<String>) {
+    if no_recorded_value(point.flags) {
+        return;
+    }
+    let mut fields = vec![format!("count={}i", point.count)];
+    fields.extend(float_field("sum", point.sum));
+    for quantile in &point.quantile_values {
+        fields.extend(float_field(&quantile_label(quantile.quantile), quantile.value));
+    }
+    push_line(lines, series(name, &point.attributes), fields, point.time_unix_nano);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue};
+    use opentelemetry_proto::tonic::metrics::v1::summary_data_point::ValueAtQuantile;
+
+    fn attribute(key: &str, value: &str) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: Some(AnyValue {
+                value: Some(any_value::Value::StringValue(value.to_string())),
+            }),
+        }
+    }
+
+    fn number(value: number_data_point::Value, attributes: Vec<KeyValue>) -> NumberDataPoint {
+        NumberDataPoint {
+            attributes,
+            time_unix_nano: 1_700_000_000_000_000_000,
+            value: Some(value),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn escape_handles_commas_spaces_and_equals() {
+        assert_eq!(escape("http requests,total", false), "http\\ requests\\,total");
+        // `=` only needs escaping in tag and field keys and tag values.
+        assert_eq!(escape("a=b", false), "a=b");
+        assert_eq!(escape("a=b", true), "a\\=b");
+        assert_eq!(escape("two\nlines", true), "two lines");
+    }
+
+    #[test]
+    fn measurement_and_tags_are_escaped_and_sorted() {
+        let point = number(
+            number_data_point::Value::AsDouble(1.5),
+            vec![attribute("route", "/a b"), attribute("k=1", "x,y"), attribute("empty", "")],
+        );
+        let mut lines = Vec::new();
+        number_point("http server,duration", &point, &mut lines);
+        assert_eq!(
+            lines,
+            vec!["http\\ server\\,duration,k\\=1=x\\,y,route=/a\\ b value=1.5 1700000000000000000"]
+        );
+    }
+
+    #[test]
+    fn integers_get_the_i_suffix_and_doubles_do_not() {
+        let mut lines = Vec::new();
+        number_point("requests", &number(number_data_point::Value::AsInt(42), vec![]), &mut lines);
+        number_point("load", &number(number_data_point::Value::AsDouble(42.0), vec![]), &mut lines);
+        let histogram = HistogramDataPoint {
+            count: 3,
+            sum: Some(7.5),
+            ..Default::default()
+        };
+        histogram_point("latency", &histogram, &mut lines);
+        assert_eq!(
+            lines,
+            vec![
+                "requests value=42i 1700000000000000000",
+                "load value=42 1700000000000000000",
+                "latency count=3i,sum=7.5",
+            ]
+        );
+    }
+
+    #[test]
+    fn non_finite_fields_are_dropped() {
+        let mut lines = Vec::new();
+        number_point("nan", &number(number_data_point::Value::AsDouble(f64::NAN), vec![]), &mut lines);
+        let summary = SummaryDataPoint {
+            count: 2,
+            sum: f64::INFINITY,
+            quantile_values: vec![
+                ValueAtQuantile { quantile: 0.5, value: 1.0 },
+                ValueAtQuantile { quantile: 0.99, value: f64::NAN },
+            ],
+            ..Default::default()
+        };
+        summary_point("rpc", &summary, &mut lines);
+        // The NaN gauge has no field left, so it has no line at all.
+        assert_eq!(lines, vec!["rpc count=2i,p50=1"]);
+    }
+
+    #[test]
+    fn datagrams_split_between_lines() {
+        let line = "x".repeat(600);
+        let lines = vec![line.clone(), line.clone(), line.clone()];
+        let packed = datagrams(&lines);
+        assert_eq!(packed, vec![format!("{0}\n{0}\n", line), format!("{}\n", line)]);
+        assert!(packed.iter().all(|datagram| datagram.len() <= MAX_DATAGRAM));
+
+        let long = "y".repeat(MAX_DATAGRAM + 10);
+        let packed = datagrams(&["a".to_string(), long.clone(), "b".to_string()]);
+        assert_eq!(packed, vec!["a\n".to_string(), format!("{}\n", long), "b\n".to_string()]);
+        assert!(datagrams(&[]).is_empty());
+    }
+}
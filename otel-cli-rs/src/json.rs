@@ -0,0 +1,138 @@
+use crate::error::DashboardError;
+use crate::metrics::{MetricKind, Temporality, UiMessage};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::io::{self, Write};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// What the dashboard prints: the TUI (or `--watch` table), or JSON lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Tui,
+    Json,
+}
+
+/// One line of `--format json` output, tagged with its `type`. Timestamps are
+/// RFC 3339 in UTC: the point's own for data points, the receive time otherwise.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    NewMetric {
+        name: &'a str,
+        timestamp: &'a str,
+    },
+    Metric {
+        name: &'a str,
+        description: &'a str,
+        unit: &'a str,
+        kind: Option<MetricKind>,
+        temporality: Option<Temporality>,
+        monotonic: Option<bool>,
+        scope: Option<&'a str>,
+        timestamp: &'a str,
+    },
+    Datapoint {
+        name: &'a str,
+        value: f64,
+        timestamp: String,
+    },
+}
+
+impl<'a> Record<'a> {
+    /// The records for one message; messages that only matter to the TUI,
+    /// such as stats and recent-update lines, have none.
+    fn of(message: &'a UiMessage, received: &'a str) -> Vec<Self> {
+        match message {
+            UiMessage::NewMetric(name) => vec![Record::NewMetric { name, timestamp: received }],
+            UiMessage::MetricInfo { name, info } => vec![Record::Metric {
+                name,
+                description: &info.description,
+                unit: &info.unit,
+                kind: info.kind,
+                temporality: info.temporality,
+                monotonic: info.monotonic,
+                scope: info.scope.as_deref(),
+                timestamp: received,
+            }],
+            UiMessage::MetricDataPointBatch(points) => points
+                .iter()
+                .map(|(name, point)| Record::Datapoint {
+                    name,
+                    value: point.value,
+                    timestamp: DateTime::from_timestamp(point.timestamp as i64, 0)
+                        .unwrap_or_default()
+                        .to_rfc3339_opts(SecondsFormat::Secs, true),
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Headless alternative to the TUI for `--format json`: one JSON object per
+/// line on stdout for every metric discovered, its metadata, and every data
+/// point graphed (histogram sums and quantiles included).
+pub async fn run_json(mut rx: UnboundedReceiver<UiMessage>) -> Result<(), DashboardError> {
+    while let Some(message) = rx.recv().await {
+        let received = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let records = Record::of(&message, &received);
+        if records.is_empty() {
+            continue;
+        }
+        let mut stdout = io::stdout().lock();
+        for record in records {
+            serde_json::to_writer(&mut stdout, &record).map_err(io::Error::from)?;
+            stdout.write_all(b"\n")?;
+        }
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{MetricInfo, MetricPoint};
+
+    const RECEIVED: &str = "2024-01-02T03:04:05.678Z";
+
+    #[test]
+    fn records_match_golden_lines() {
+        let messages = vec![
+            UiMessage::NewMetric("http.requests".to_string()),
+            UiMessage::MetricInfo {
+                name: "http.requests".to_string(),
+                info: MetricInfo {
+                    description: "Requests served".to_string(),
+                    unit: "{request}".to_string(),
+                    kind: Some(MetricKind::Sum),
+                    temporality: Some(Temporality::Cumulative),
+                    monotonic: Some(true),
+                    scope: Some("api@1.2.0".to_string()),
+                    start_time: None,
+                },
+            },
+            UiMessage::MetricDataPointBatch(vec![
+                ("http.requests".to_string(), MetricPoint { timestamp: 1_704_164_645, value: 42.0 }),
+                ("http.requests".to_string(), MetricPoint { timestamp: 1_704_164_650, value: 42.5 }),
+            ]),
+            UiMessage::MetricUpdate {
+                name: "http.requests".to_string(),
+                update: "http.requests: = 42.5".to_string(),
+                source: None,
+                listener: None,
+            },
+        ];
+
+        let mut lines = String::new();
+        for message in &messages {
+            for record in Record::of(message, RECEIVED) {
+                lines.push_str(&serde_json::to_string(&record).unwrap());
+                lines.push('\n');
+            }
+        }
+        assert_eq!(lines, include_str!("../tests/fixtures/json_records.jsonl"));
+    }
+}
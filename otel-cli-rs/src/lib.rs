@@ -0,0 +1,28 @@
+pub mod alert;
+pub mod alias;
+mod capture;
+mod clipboard;
+mod command;
+pub mod config;
+pub mod error;
+mod export;
+mod forward;
+mod fuzzy;
+mod influx;
+pub mod json;
+pub mod http;
+pub mod metrics;
+mod otlp_json;
+mod rate_limit;
+pub mod reflection;
+pub mod replay;
+pub mod retention;
+mod rule;
+pub mod self_metrics;
+pub mod selftest;
+pub mod session;
+pub mod stdin;
+pub mod threshold;
+#[cfg(test)]
+mod testutil;
+pub mod ui;
@@ -1,50 +1,793 @@
 use clap::Parser;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use opentelemetry_proto::tonic::collector::metrics::v1::metrics_service_server::MetricsServiceServer;
 use tonic::transport::Server;
-use crate::error::DashboardError;
+use tower::util::MapResponseLayer;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use otel_dashboard::error::DashboardError;
+use tokio::net::TcpListener;
 use tokio::sync::mpsc;
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_stream::{StreamExt, StreamMap};
 
-mod error;
-mod metrics;
-mod ui;
+use otel_dashboard::{
+    alert, alias, config, http, json, metrics, reflection, replay, retention, self_metrics, selftest,
+    session, stdin, threshold, ui,
+};
+
+/// `--version` output; `-V` prints only the crate version.
+const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ncommit: ",
+    env!("BUILD_GIT_COMMIT"),
+    "\nbuilt: ",
+    env!("BUILD_DATE"),
+    "\nopentelemetry-proto: ",
+    env!("BUILD_OTEL_PROTO_VERSION"),
+);
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, long_version = LONG_VERSION, about, long_about = None)]
 struct Args {
-    #[arg(short, long, default_value = "127.0.0.1:4317")]
-    address: SocketAddr,
+    /// Address to bind the OTLP receiver [default: 127.0.0.1:4317]; repeatable to
+    /// listen on several at once
+    #[arg(short, long)]
+    address: Vec<SocketAddr>,
 
     #[arg(short, long)]
     debug: bool,
+
+    /// Only log errors
+    #[arg(short, long, conflicts_with = "debug")]
+    quiet: bool,
+
+    /// Append logs to this file; while the TUI is up they are dropped otherwise
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<std::path::PathBuf>,
+
+    /// Also accept OTLP/HTTP (protobuf or JSON) on this address, e.g. 127.0.0.1:4318
+    #[arg(long)]
+    http_address: Option<SocketAddr>,
+
+    /// Plot cumulative sums as the increase between consecutive points
+    #[arg(long)]
+    cumulative_to_delta: bool,
+
+    /// Only ingest metrics whose name matches this regex
+    #[arg(long)]
+    include: Option<String>,
+
+    /// Skip metrics whose name matches this regex
+    #[arg(long)]
+    exclude: Option<String>,
+
+    /// Export the dashboard's own counters as OTLP to this gRPC endpoint, e.g. http://localhost:4317
+    #[arg(long)]
+    self_metrics: Option<String>,
+
+    /// Also re-export every received request to this downstream OTLP gRPC collector, e.g. http://collector:4317
+    #[arg(long, value_name = "ENDPOINT")]
+    forward: Option<String>,
+
+    /// Also write every data point as InfluxDB line protocol to this file, or to a
+    /// udp://host:port listener
+    #[arg(long, value_name = "PATH|UDP-ENDPOINT")]
+    influx_out: Option<String>,
+
+    /// Seconds between --self-metrics exports (default: 10)
+    #[arg(long, value_name = "SECS")]
+    self_metrics_interval: Option<u64>,
+
+    /// Highlight a metric when it crosses a threshold, e.g. "http.latency>100"; repeatable
+    #[arg(long = "alert", value_name = "RULE")]
+    alerts: Vec<alert::AlertRule>,
+
+    /// Show a metric under a shorter name in the list, e.g. "process.runtime.jvm.memory.usage=JVM mem"; repeatable
+    #[arg(long = "alias", value_name = "NAME=ALIAS")]
+    aliases: Vec<alias::MetricAlias>,
+
+    /// Draw a dashed reference line across a metric's graph and highlight the points
+    /// above it, e.g. "http.latency=250"; repeatable
+    #[arg(long = "threshold", value_name = "NAME=VALUE")]
+    thresholds: Vec<threshold::Threshold>,
+
+    /// Reload the previous session's view (selected metric, chart style, window, history...) and save it on exit
+    #[arg(long)]
+    restore_session: bool,
+
+    /// Show one pane at a time (the list, or the graph of the selected metric) for small terminals
+    #[arg(long)]
+    compact: bool,
+
+    /// Percent of the screen height given to the metric list (default 30); `<` and `>` adjust it
+    #[arg(long, value_name = "PCT", value_parser = clap::value_parser!(u16).range(10..=90))]
+    split: Option<u16>,
+
+    /// List metrics in the order they first arrived instead of alphabetically
+    #[arg(long)]
+    no_sort: bool,
+
+    /// Scale overlaid graph series to [0, 1] so different magnitudes compare by shape
+    #[arg(long)]
+    normalize: bool,
+
+    /// Number of points kept per metric (default 100); long histories are downsampled when drawn
+    #[arg(long, value_name = "POINTS")]
+    history: Option<usize>,
+
+    /// Keep a different number of points for one metric, e.g. "http.latency=1000"; repeatable
+    #[arg(long = "history-for", value_name = "NAME=POINTS")]
+    history_for: Vec<retention::HistoryOverride>,
+
+    /// Reject export requests beyond this many per second with RESOURCE_EXHAUSTED
+    #[arg(long, value_name = "RPS", value_parser = clap::value_parser!(u64).range(1..))]
+    rate_limit: Option<u64>,
+
+    /// Fail this fraction (0.0-1.0) of export requests with UNAVAILABLE, to exercise exporter retries
+    #[arg(long, value_name = "RATE")]
+    fail_rate: Option<f64>,
+
+    /// Largest export request accepted, in bytes (default 4MB over gRPC, 2MB over HTTP)
+    #[arg(long, value_name = "BYTES")]
+    max_recv_size: Option<usize>,
+
+    /// Maximum number of series kept (default 1000); new series beyond it are dropped
+    #[arg(long, value_name = "N")]
+    max_series: Option<usize>,
+
+    /// Remove metrics whose latest point is older than this many seconds; they are dimmed first
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    stale_timeout: Option<u64>,
+
+    /// Count the updates of the last SECS seconds in the updates title (default: 10)
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    update_window: Option<u64>,
+
+    /// Warn about metrics with more than N distinct attribute sets
+    #[arg(long, value_name = "N")]
+    cardinality_warn: Option<usize>,
+
+    /// Time zone for graph axis labels and report timestamps
+    #[arg(long, value_enum, value_name = "ZONE")]
+    timezone: Option<ui::Timezone>,
+
+    /// Series colors for a dark or light background (default: auto, from COLORFGBG, else dark)
+    #[arg(long, value_enum, value_name = "THEME")]
+    theme: Option<ui::ThemeChoice>,
+
+    /// Pane shown when a metric is selected (default: graph); `u` switches to the other
+    #[arg(long, value_enum, value_name = "VIEW")]
+    default_view: Option<ui::DefaultView>,
+
+    /// Apply at most this many received messages per frame, spreading bursts over several frames
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_per_frame: Option<u64>,
+
+    /// Don't store a point equal to the previous one (within EPSILON, default 0); the
+    /// stored run's end time still advances, so history covers more wall-clock time
+    #[arg(long, value_name = "EPSILON", num_args = 0..=1, default_missing_value = "0")]
+    dedup: Option<f64>,
+
+    /// Redraw the TUI as soon as data or a key arrives instead of every refresh interval
+    #[arg(long)]
+    realtime: bool,
+
+    /// Skip data points repeated within one export request (same metric, attributes,
+    /// timestamp and value), as some buggy exporters send
+    #[arg(long)]
+    dedup_batch: bool,
+
+    /// Show values with exactly N decimals (axis labels, stats, updates); very large or
+    /// small values switch to scientific notation
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(0..=12))]
+    precision: Option<u8>,
+
+    /// Clear recent updates whenever the selected metric changes instead of keeping them per metric
+    #[arg(long)]
+    clear_updates_on_select: bool,
+
+    /// Write each data point to the updates feed as `name{key=value,...} = value`
+    #[arg(long)]
+    flatten_attributes: bool,
+
+    /// Prefix each recent update with the IP address of the exporter that sent it
+    #[arg(long)]
+    show_source: bool,
+
+    /// Instead of the TUI, print a table of every metric's latest value every SECS seconds
+    #[arg(long, value_name = "SECS", conflicts_with = "selftest")]
+    watch: Option<u64>,
+
+    /// Output format: the TUI (default), or one JSON object per line on stdout
+    /// for every new metric, its metadata and every data point
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["selftest", "watch"])]
+    format: Option<json::OutputFormat>,
+
+    /// Accept exactly one export request, print a summary of it and exit
+    #[arg(long, conflicts_with_all = ["selftest", "watch", "list_duration"])]
+    one_shot: bool,
+
+    /// Receive for SECS seconds without the TUI, print every metric name seen (sorted) and exit
+    #[arg(long, value_name = "SECS", conflicts_with_all = ["selftest", "watch"])]
+    list_duration: Option<u64>,
+
+    /// Send a synthetic export to ourselves, print OK and exit
+    #[arg(long)]
+    selftest: bool,
+
+    /// Don't serve the standard gRPC health service (grpc.health.v1.Health)
+    #[arg(long)]
+    no_health: bool,
+
+    /// Serve gRPC server reflection, so grpcurl and grpcui can call the receiver without proto files
+    #[arg(long)]
+    reflection: bool,
+
+    /// Listen on a Unix domain socket at this path instead of TCP
+    #[cfg(unix)]
+    #[arg(long, conflicts_with_all = ["selftest", "stdin", "replay_csv"])]
+    uds: Option<std::path::PathBuf>,
+
+    /// Read length-delimited export requests (the --capture format) from stdin instead of
+    /// listening for OTLP/gRPC, and exit when stdin closes
+    #[arg(long, conflicts_with_all = ["selftest", "one_shot", "port_file"])]
+    stdin: bool,
+
+    /// Show the `timestamp,metric,value` rows of a CSV file (or a file written by `e`)
+    /// instead of listening for OTLP/gRPC
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["selftest", "one_shot", "port_file", "stdin", "list_duration"]
+    )]
+    replay_csv: Option<std::path::PathBuf>,
+
+    /// Append every received export request to this file as length-delimited protobuf
+    #[arg(long)]
+    capture: Option<std::path::PathBuf>,
+
+    /// Write the bound TCP port to this file (useful with --address 127.0.0.1:0)
+    #[arg(long)]
+    port_file: Option<std::path::PathBuf>,
+
+    /// Load settings from a TOML file; command-line flags take precedence
+    #[arg(long)]
+    config: Option<std::path::PathBuf>,
+}
+
+const DEFAULT_ADDRESS: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 4317));
+
+impl Args {
+    /// Fills every flag not given on the command line from the config file.
+    fn merge(&mut self, file: config::FileConfig) {
+        if self.address.is_empty() {
+            self.address = file.address.unwrap_or_default();
+        }
+        self.http_address = self.http_address.or(file.http_address);
+        self.debug = self.debug || file.debug.unwrap_or(false);
+        self.quiet = self.quiet || file.quiet.unwrap_or(false);
+        self.log_file = self.log_file.take().or(file.log_file);
+        self.no_health = self.no_health || file.no_health.unwrap_or(false);
+        self.reflection = self.reflection || file.reflection.unwrap_or(false);
+        self.cumulative_to_delta = self.cumulative_to_delta || file.cumulative_to_delta.unwrap_or(false);
+        self.include = self.include.take().or(file.include);
+        self.exclude = self.exclude.take().or(file.exclude);
+        self.self_metrics = self.self_metrics.take().or(file.self_metrics);
+        self.self_metrics_interval = self.self_metrics_interval.or(file.self_metrics_interval);
+        self.forward = self.forward.take().or(file.forward);
+        self.influx_out = self.influx_out.take().or(file.influx_out);
+        self.port_file = self.port_file.take().or(file.port_file);
+        self.capture = self.capture.take().or(file.capture);
+        self.normalize = self.normalize || file.normalize.unwrap_or(false);
+        self.compact = self.compact || file.compact.unwrap_or(false);
+        self.split = self.split.or(file.split);
+        self.no_sort = self.no_sort || file.no_sort.unwrap_or(false);
+        self.restore_session = self.restore_session || file.restore_session.unwrap_or(false);
+        self.watch = self.watch.or(file.watch);
+        self.format = self.format.or(file.format);
+        self.show_source = self.show_source || file.show_source.unwrap_or(false);
+        self.flatten_attributes = self.flatten_attributes || file.flatten_attributes.unwrap_or(false);
+        self.history = self.history.or(file.history);
+        self.max_series = self.max_series.or(file.max_series);
+        self.stale_timeout = self.stale_timeout.or(file.stale_timeout);
+        self.update_window = self.update_window.or(file.update_window);
+        self.cardinality_warn = self.cardinality_warn.or(file.cardinality_warn);
+        self.max_per_frame = self.max_per_frame.or(file.max_per_frame);
+        self.dedup = self.dedup.or(file.dedup);
+        self.dedup_batch = self.dedup_batch || file.dedup_batch.unwrap_or(false);
+        self.realtime = self.realtime || file.realtime.unwrap_or(false);
+        self.precision = self.precision.or(file.precision);
+        self.timezone = self.timezone.or(file.timezone);
+        self.theme = self.theme.or(file.theme);
+        self.default_view = self.default_view.or(file.default_view);
+        self.max_recv_size = self.max_recv_size.or(file.max_recv_size);
+        self.rate_limit = self.rate_limit.or(file.rate_limit);
+        self.fail_rate = self.fail_rate.or(file.fail_rate);
+        self.clear_updates_on_select =
+            self.clear_updates_on_select || file.clear_updates_on_select.unwrap_or(false);
+        if self.alerts.is_empty() {
+            self.alerts = file.alert.unwrap_or_default();
+        }
+        if self.aliases.is_empty() {
+            self.aliases = file.alias.unwrap_or_default();
+        }
+        if self.thresholds.is_empty() {
+            self.thresholds = file.threshold.unwrap_or_default();
+        }
+        if self.history_for.is_empty() {
+            self.history_for = file.history_for.unwrap_or_default();
+        }
+        #[cfg(unix)]
+        {
+            self.uds = self.uds.take().or(file.uds);
+        }
+    }
+}
+
+/// Binds the Unix socket at `path`. A socket file left behind by a run that
+/// didn't exit cleanly is removed first, unless something still answers on it;
+/// anything other than a socket is left alone and fails the bind.
+#[cfg(unix)]
+fn bind_uds(path: &std::path::Path) -> Result<tokio::net::UnixListener, DashboardError> {
+    use std::os::unix::fs::FileTypeExt;
+    let is_socket = std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket());
+    if is_socket {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(DashboardError::Config(format!(
+                "{} is in use by another process",
+                path.display()
+            )));
+        }
+        tracing::info!("Removing stale socket {}", path.display());
+        std::fs::remove_file(path)?;
+    }
+    Ok(tokio::net::UnixListener::bind(path)?)
+}
+
+/// Binds the gRPC listener up front so the port the OS picked for `:0` is known
+/// before serving. That address is printed to stdout and, if requested, the
+/// port is written to `port_file`.
+async fn bind_tcp(
+    addr: SocketAddr,
+    port_file: Option<&std::path::Path>,
+) -> Result<(TcpListener, SocketAddr), DashboardError> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    if addr.port() == 0 {
+        println!("{}", local_addr);
+    }
+    if let Some(path) = port_file {
+        std::fs::write(path, format!("{}\n", local_addr.port()))?;
+    }
+    Ok((listener, local_addr))
+}
+
+/// Binds every `--address`, merged into one stream of connections for a single
+/// server; tonic still reports which listener each request arrived on. Only
+/// the first listener's port goes to `port_file`.
+async fn bind_all(
+    addresses: &[SocketAddr],
+    port_file: Option<&std::path::Path>,
+) -> Result<(StreamMap<usize, TcpListenerStream>, Vec<SocketAddr>), DashboardError> {
+    let mut listeners = StreamMap::new();
+    let mut bound = Vec::new();
+    for (i, addr) in addresses.iter().enumerate() {
+        let (listener, local_addr) = bind_tcp(*addr, port_file.filter(|_| i == 0)).await?;
+        tracing::info!("Starting OTLP receiver on {}", local_addr);
+        listeners.insert(i, TcpListenerStream::new(listener));
+        bound.push(local_addr);
+    }
+    Ok((listeners, bound))
+}
+
+/// Turns on TCP_NODELAY, which tonic only sets on listeners it binds itself.
+/// Without it a response can sit out the client's delayed ACK (~40ms) when
+/// several exports share a connection.
+fn no_delay(conn: std::io::Result<tokio::net::TcpStream>) -> std::io::Result<tokio::net::TcpStream> {
+    let conn = conn?;
+    conn.set_nodelay(true)?;
+    Ok(conn)
+}
+
+/// The `--address` list, or the default one. Port `0` may repeat, since each
+/// gets its own free port.
+fn addresses(args: &Args) -> Result<Vec<SocketAddr>, DashboardError> {
+    if args.address.is_empty() {
+        return Ok(vec![DEFAULT_ADDRESS]);
+    }
+    let mut seen = std::collections::HashSet::new();
+    for addr in args.address.iter().filter(|addr| addr.port() != 0) {
+        if !seen.insert(addr) {
+            return Err(DashboardError::Config(format!("--address {} is given more than once", addr)));
+        }
+    }
+    Ok(args.address.clone())
+}
+
+/// Resolves on SIGTERM, e.g. when a supervisor stops the process.
+#[cfg(unix)]
+async fn terminate_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            sigterm.recv().await;
+        }
+        Err(_) => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminate_signal() {
+    std::future::pending().await
+}
+
+/// Where exporters can reach the receiver, shown in the TUI because the
+/// startup log line ends up behind the alternate screen.
+fn listening(args: &Args, addresses: &[SocketAddr]) -> String {
+    #[cfg(unix)]
+    let uds = args.uds.as_ref().map(|path| format!("gRPC unix:{}", path.display()));
+    #[cfg(not(unix))]
+    let uds: Option<String> = None;
+
+    let grpc = if args.stdin {
+        "stdin".to_string()
+    } else if let Some(path) = &args.replay_csv {
+        format!("replay of {}", path.display())
+    } else {
+        uds.unwrap_or_else(|| {
+            let urls: Vec<String> = addresses.iter().map(|addr| format!("http://{}", addr)).collect();
+            format!("gRPC {}", urls.join(" "))
+        })
+    };
+    match args.http_address {
+        Some(http_address) => format!("{}, HTTP http://{}/v1/metrics", grpc, http_address),
+        None => grpc,
+    }
 }
 
 #[tokio::main]
-async fn main() -> Result<(), DashboardError> {
-    let args = Args::parse();
+async fn main() {
+    install_panic_hook();
+    if let Err(e) = run().await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Restores the terminal before the default hook prints the panic, so a panic
+/// while the TUI is up leaves a usable shell and a readable message.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        ui::restore_terminal();
+        default_hook(info);
+    }));
+}
 
-    let log_level = if args.debug { "debug" } else { "info" };
+async fn run() -> Result<(), DashboardError> {
+    let mut args = Args::parse();
+    if let Some(path) = args.config.clone() {
+        args.merge(config::load(&path)?);
+    }
+
+    let log_level = if args.debug {
+        "debug"
+    } else if args.quiet {
+        "error"
+    } else {
+        "info"
+    };
+    let json = args.format == Some(json::OutputFormat::Json);
+    let tui = !json
+        && args.watch.is_none()
+        && args.list_duration.is_none()
+        && !args.one_shot
+        && !args.selftest;
+    // Log lines written to the terminal would land on the TUI's alternate
+    // screen, and with `--format json` stdout is kept to JSON lines alone.
+    let log_writer = match &args.log_file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new().create(true).append(true).open(path).map_err(|e| {
+                DashboardError::Config(format!("cannot open log file {}: {}", path.display(), e))
+            })?;
+            BoxMakeWriter::new(std::sync::Mutex::new(file))
+        }
+        None if tui => BoxMakeWriter::new(std::io::sink),
+        None if json => BoxMakeWriter::new(std::io::stderr),
+        None => BoxMakeWriter::new(std::io::stdout),
+    };
     tracing_subscriber::fmt()
         .with_env_filter(log_level)
+        .with_writer(log_writer)
+        .with_ansi(args.log_file.is_none())
         .init();
 
-    let (tx, rx) = mpsc::unbounded_channel();
-    let tui_handle = tokio::spawn(ui::run_tui(rx));
+    // Read up front so a missing or unreadable file fails before the TUI starts.
+    let replay = match &args.replay_csv {
+        Some(path) => Some((path.clone(), replay::load_csv(path)?)),
+        None => None,
+    };
+    // Points are sorted, so the last one is the newest.
+    let replay_end = replay.as_ref().and_then(|(_, points)| points.last()).map(|(_, point)| point.timestamp);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let replay_tx = tx.clone();
+    let (control_tx, control_rx) = mpsc::unbounded_channel();
+
+    let mut addresses = addresses(&args)?;
+    let receiver_options = metrics::ReceiverOptions {
+        debug_mode: args.debug,
+        cumulative_to_delta: args.cumulative_to_delta,
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        capture: args.capture.clone(),
+        rate_limit: args.rate_limit,
+        fail_rate: args.fail_rate.unwrap_or(0.0),
+        flatten_attributes: args.flatten_attributes,
+        forward: args.forward.clone(),
+        precision: args.precision.map(usize::from),
+        influx_out: args.influx_out.clone(),
+        stale_timeout: args.stale_timeout.map(std::time::Duration::from_secs),
+        dedup_batch: args.dedup_batch,
+    };
+    let receiver = metrics::create_receiver(receiver_options, tx, control_rx)?;
+    // Registered before serving so the very first request is the one reported.
+    let first_export = args.one_shot.then(|| receiver.first_export());
+    let metrics_service = metrics::create_metrics_service(receiver.clone(), args.max_recv_size);
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    let reflection = args.reflection.then(|| reflection::service(!args.no_health)).transpose()?;
+    let router = Server::builder()
+        .layer(MapResponseLayer::new(metrics::log_rejected_export))
+        .add_service(metrics_service)
+        .add_optional_service((!args.no_health).then_some(health_service))
+        .add_optional_service(reflection);
+
+    // Lets `--one-shot` stop the server gracefully, after its response is sent.
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let stop_signal = async {
+        let _ = stop_rx.await;
+    };
+
+    // With `--stdin` the input task stands in for the gRPC server, so the
+    // process ends when either of them does.
+    let server_handle = if args.stdin {
+        let receiver = receiver.clone();
+        let max_frame = args.max_recv_size;
+        tracing::info!("Reading OTLP export requests from stdin");
+        tokio::spawn(async move {
+            match stdin::read(receiver, max_frame).await {
+                Ok(requests) => tracing::info!("stdin closed after {} export request(s)", requests),
+                Err(e) => tracing::error!("Failed to read from stdin: {}", e),
+            }
+            Ok(())
+        })
+    } else if let Some((path, points)) = replay {
+        // Points go straight to the UI channel, so nothing is served; the task
+        // then idles to keep the dashboard open on the replayed data.
+        tokio::spawn(async move {
+            match replay::replay(&path, points, &replay_tx) {
+                Ok(points) => tracing::info!("Replayed {} point(s) from {}", points, path.display()),
+                Err(e) => tracing::error!("Failed to replay {}: {}", path.display(), e),
+            }
+            std::future::pending::<()>().await;
+            Ok(())
+        })
+    } else {
+        #[cfg(unix)]
+        let server_handle = match &args.uds {
+            Some(path) => {
+                let listener = bind_uds(path)?;
+                tracing::info!("Starting OTLP receiver on unix:{}", path.display());
+                tokio::spawn(router.serve_with_incoming_shutdown(
+                    tokio_stream::wrappers::UnixListenerStream::new(listener),
+                    stop_signal,
+                ))
+            }
+            None => {
+                let (listeners, bound) = bind_all(&addresses, args.port_file.as_deref()).await?;
+                addresses = bound;
+                tokio::spawn(router.serve_with_incoming_shutdown(listeners.map(|(_, conn)| no_delay(conn)), stop_signal))
+            }
+        };
+
+        #[cfg(not(unix))]
+        let server_handle = {
+            let (listeners, bound) = bind_all(&addresses, args.port_file.as_deref()).await?;
+            addresses = bound;
+            tokio::spawn(router.serve_with_incoming_shutdown(listeners.map(|(_, conn)| no_delay(conn)), stop_signal))
+        };
+
+        if !args.no_health {
+            health_reporter
+                .set_serving::<MetricsServiceServer<metrics::MetricsReceiver>>()
+                .await;
+        }
+        server_handle
+    };
 
-    let addr = args.address;
-    let metrics_service = metrics::create_metrics_service(args.debug, tx);
+    if let Some(endpoint) = args.self_metrics.clone() {
+        let interval = std::time::Duration::from_secs(args.self_metrics_interval.unwrap_or(10).max(1));
+        tokio::spawn(self_metrics::run(endpoint, interval, receiver.clone()));
+    }
+
+    if let Some(http_address) = args.http_address {
+        tokio::spawn(http::serve(http_address, receiver.clone(), args.max_recv_size));
+    }
+
+    if args.selftest {
+        let result = selftest::run(addresses[0], rx).await;
+        server_handle.abort();
+        receiver.shutdown().await;
+        result?;
+        println!("OK");
+        return Ok(());
+    }
+
+    if let Some(first_export) = first_export {
+        tokio::spawn(async move { while rx.recv().await.is_some() {} });
+        let summary = first_export.await;
+        let _ = stop_tx.send(());
+        let _ = server_handle.await;
+        receiver.shutdown().await;
+        let summary = summary
+            .map_err(|_| DashboardError::Channel("receiver dropped before the first export".to_string()))?;
+        println!("{}", summary);
+        return Ok(());
+    }
 
-    tracing::info!("Starting OTLP receiver on {}", addr);
+    if let Some(secs) = args.list_duration {
+        // Nothing reads the UI channel in this mode; drain it so sends don't fail.
+        tokio::spawn(async move { while rx.recv().await.is_some() {} });
+        tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+        server_handle.abort();
+        for name in receiver.seen_metric_names() {
+            println!("{}", name);
+        }
+        receiver.shutdown().await;
+        return Ok(());
+    }
 
-    let server_handle = tokio::spawn(
-        Server::builder()
-            .add_service(metrics_service)
-            .serve(addr)
-    );
+    let tui_options = ui::TuiOptions {
+        alerts: args.alerts.clone(),
+        aliases: args.aliases.clone(),
+        thresholds: args.thresholds.clone(),
+        normalize: args.normalize,
+        show_source: args.show_source,
+        show_listener: addresses.len() > 1,
+        history: args.history,
+        history_for: args.history_for.clone(),
+        clear_updates_on_select: args.clear_updates_on_select,
+        max_series: args.max_series,
+        compact: args.compact,
+        split: args.split,
+        no_sort: args.no_sort,
+        stale_timeout: args.stale_timeout.map(std::time::Duration::from_secs),
+        replay_end,
+        update_window: args.update_window.map(std::time::Duration::from_secs),
+        cardinality_warn: args.cardinality_warn,
+        max_per_frame: args.max_per_frame.map(|n| n as usize),
+        realtime: args.realtime,
+        dedup_epsilon: args.dedup.map(f64::abs),
+        precision: args.precision.map(usize::from),
+        timezone: args.timezone.unwrap_or_default(),
+        theme: args.theme,
+        default_view: args.default_view.unwrap_or_default(),
+        listening: listening(&args, &addresses),
+        session: if args.restore_session { Some(session::load()?) } else { None },
+    };
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    let mut tui_handle = match args.watch {
+        _ if json => tokio::spawn(json::run_json(rx)),
+        Some(secs) => {
+            let interval = std::time::Duration::from_secs(secs.max(1));
+            tokio::spawn(ui::run_watch(rx, interval, tui_options))
+        }
+        None => tokio::spawn(ui::run_tui(rx, control_tx, shutdown_rx, tui_options)),
+    };
 
     tokio::select! {
-        _ = tui_handle => println!("TUI closed"),
-        _ = server_handle => println!("Server closed"),
+        _ = &mut tui_handle => if !json { println!("TUI closed") },
+        _ = server_handle => {
+            let closed = if args.stdin { "Input closed" } else { "Server closed" };
+            if json { eprintln!("{}", closed) } else { println!("{}", closed) }
+        }
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate_signal() => {}
+    }
+    // Anything but the TUI quitting by itself: let it leave raw mode and the
+    // alternate screen before exiting.
+    if !tui_handle.is_finished() {
+        let _ = shutdown_tx.send(());
+        if args.watch.is_some() || json {
+            tui_handle.abort();
+        }
+        let _ = tui_handle.await;
+    }
+
+    receiver.shutdown().await;
+
+    #[cfg(unix)]
+    if let Some(path) = &args.uds {
+        let _ = std::fs::remove_file(path);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv6Addr};
+
+    #[tokio::test]
+    async fn binds_ipv6_loopback() {
+        let (_listener, bound) = bind_tcp("[::1]:0".parse().unwrap(), None).await.unwrap();
+        assert_eq!(bound.ip(), IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_ne!(bound.port(), 0);
+        tokio::net::TcpStream::connect(bound).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn binds_ipv6_wildcard() {
+        let (_listener, bound) = bind_tcp("[::]:0".parse().unwrap(), None).await.unwrap();
+        assert_eq!(bound.ip(), IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+        assert_ne!(bound.port(), 0);
+        let loopback = SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), bound.port());
+        tokio::net::TcpStream::connect(loopback).await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn stale_socket_file_is_replaced_but_a_live_one_is_not() {
+        let path = std::env::temp_dir().join(format!("otel-dashboard-stale-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        // Dropping the listener leaves the file behind, as a crash would.
+        drop(bind_uds(&path).unwrap());
+        assert!(path.exists());
+        let listener = bind_uds(&path).unwrap();
+        assert!(bind_uds(&path).is_err());
+        drop(listener);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn regular_file_at_the_socket_path_is_kept() {
+        let path = std::env::temp_dir().join(format!("otel-dashboard-file-{}.sock", std::process::id()));
+        std::fs::write(&path, "not a socket").unwrap();
+        assert!(bind_uds(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn merged(argv: &[&str], file: &str) -> Args {
+        let mut args = Args::try_parse_from(argv).unwrap();
+        args.merge(toml::from_str(file).unwrap());
+        args
+    }
+
+    #[test]
+    fn explicit_zero_fail_rate_beats_the_config_file() {
+        let args = merged(&["otel-dashboard", "--fail-rate", "0"], "fail_rate = 0.5");
+        assert_eq!(args.fail_rate, Some(0.0));
+        assert_eq!(merged(&["otel-dashboard"], "fail_rate = 0.5").fail_rate, Some(0.5));
+    }
+
+    #[test]
+    fn self_metrics_interval_comes_from_the_config_file_unless_given() {
+        let file = "self_metrics_interval = 30";
+        assert_eq!(merged(&["otel-dashboard"], file).self_metrics_interval, Some(30));
+        let cli = merged(&["otel-dashboard", "--self-metrics-interval", "5"], file);
+        assert_eq!(cli.self_metrics_interval, Some(5));
+    }
+
+    #[test]
+    fn config_file_gives_one_or_several_addresses() {
+        let one = merged(&["otel-dashboard"], r#"address = "127.0.0.1:4317""#);
+        assert_eq!(one.address, vec!["127.0.0.1:4317".parse::<SocketAddr>().unwrap()]);
+        let many = merged(&["otel-dashboard"], r#"address = ["127.0.0.1:4317", "[::1]:4317"]"#);
+        assert_eq!(many.address.len(), 2);
+        let cli = merged(&["otel-dashboard", "--address", "127.0.0.1:9000"], r#"address = ["[::1]:4317"]"#);
+        assert_eq!(cli.address, vec!["127.0.0.1:9000".parse::<SocketAddr>().unwrap()]);
+    }
+}
+
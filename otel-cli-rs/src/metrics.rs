@@ -2,40 +2,782 @@ use opentelemetry_proto::tonic::collector::metrics::v1::{
     metrics_service_server::{MetricsService, MetricsServiceServer},
     ExportMetricsServiceRequest, ExportMetricsServiceResponse,
 };
-use tokio::sync::{mpsc::UnboundedSender, Mutex as TokioMutex};
-use tonic::{Request, Response, Status};
-use std::collections::HashSet;
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue};
+use opentelemetry_proto::tonic::metrics::v1::{
+    metric::Data, number_data_point, AggregationTemporality, DataPointFlags, ExponentialHistogramDataPoint,
+    HistogramDataPoint, Metric, NumberDataPoint, Sum, SummaryDataPoint,
+};
+use tokio::sync::{
+    mpsc::{UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
+use crate::capture::CaptureWriter;
+use crate::forward::Forwarder;
+use crate::influx::{self, InfluxWriter};
+use crate::error::DashboardError;
+use crate::rate_limit::{RateLimitCounts, RateLimiter};
+use prost::Message;
+use regex::Regex;
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::{Code, Request, Response, Status};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::fmt;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone)]
+/// Quantiles derived from histogram buckets, each sent as its own series.
+pub const HISTOGRAM_QUANTILES: [(f64, &str); 3] = [(0.5, "p50"), (0.9, "p90"), (0.99, "p99")];
+
+/// Name of the derived series holding a histogram or summary quantile, e.g. `latency[p99]`.
+pub fn quantile_series_name(metric_name: &str, label: &str) -> String {
+    format!("{}[{}]", metric_name, label)
+}
+
+fn lock<T>(mutex: &StdMutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Update text for a data point flagged `NO_RECORDED_VALUE`.
+const NO_RECORDED_VALUE: &str = "no recorded value";
+
+/// Percentile label for a summary quantile, e.g. `0.999` becomes `p99.9`.
+pub fn quantile_label(quantile: f64) -> String {
+    format!("p{}", (quantile * 1e6).round() / 1e4)
+}
+
+pub fn format_any_value(value: &AnyValue) -> String {
+    match &value.value {
+        Some(any_value::Value::StringValue(v)) => v.clone(),
+        Some(any_value::Value::BoolValue(v)) => v.to_string(),
+        Some(any_value::Value::IntValue(v)) => v.to_string(),
+        Some(any_value::Value::DoubleValue(v)) => v.to_string(),
+        Some(any_value::Value::ArrayValue(v)) => format!(
+            "[{}]",
+            v.values.iter().map(format_any_value).collect::<Vec<_>>().join(",")
+        ),
+        Some(any_value::Value::KvlistValue(v)) => format!("{{{}}}", format_attributes(&v.values)),
+        Some(any_value::Value::BytesValue(v)) => format!("<{} bytes>", v.len()),
+        None => String::new(),
+    }
+}
+
+/// Renders an attribute set as `key=value` pairs separated by commas.
+pub fn format_attributes(attributes: &[KeyValue]) -> String {
+    attributes
+        .iter()
+        .map(|kv| {
+            let value = kv.value.as_ref().map(format_any_value).unwrap_or_default();
+            format!("{}={}", kv.key, value)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+const LARGE_PREFIXES: [(f64, &str); 5] = [(1e15, "P"), (1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "k")];
+const SMALL_PREFIXES: [(f64, &str); 4] = [(1e-3, "m"), (1e-6, "µ"), (1e-9, "n"), (1e-12, "p")];
+
+/// Decimals of plain numbers (axis labels, `--watch`) without `--precision`.
+pub const DEFAULT_PRECISION: usize = 2;
+
+/// `value` with `precision` decimals, switching to scientific notation when
+/// it is too large to read or so small that the decimals would all be zero.
+pub fn format_decimal(value: f64, precision: usize) -> String {
+    let magnitude = value.abs();
+    let tiny = magnitude > 0.0 && magnitude < 10f64.powi(-(precision.min(300) as i32));
+    if value.is_finite() && (magnitude >= 1e9 || tiny) {
+        format!("{:.*e}", precision, value)
+    } else {
+        format!("{:.*}", precision, value)
+    }
+}
+
+/// Renders a value with an SI prefix and its unit, e.g. `1.23M` or `4.5ms`.
+/// Sub-second time units are folded into seconds first, so `4500` with unit
+/// `ms` prints as `4.5s`; time never gets the large prefixes (`4500s`, not
+/// `4.5ks`). Dimensionless values (`1`, `{requests}`, empty) never get the
+/// small prefixes: `0.25` stays `0.25` rather than `250m`. With `precision`
+/// the number gets that many decimals instead of three significant digits.
+pub fn format_value(value: f64, unit: &str, precision: Option<usize>) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let (value, unit) = match unit {
+        "ms" => (value / 1e3, "s"),
+        "us" => (value / 1e6, "s"),
+        "ns" => (value / 1e9, "s"),
+        "By" => (value, "B"),
+        "1" => (value, ""),
+        u if u.starts_with('{') && u.ends_with('}') => (value, ""),
+        u => (value, u),
+    };
+    if unit == "%" {
+        return format!("{}%", trim_number(value, precision));
+    }
+
+    let time = matches!(unit, "s" | "min" | "h" | "d");
+    let mut scales = Vec::new();
+    if !unit.is_empty() && (!time || unit == "s") {
+        scales.extend(SMALL_PREFIXES.iter().rev());
+    }
+    let unscaled = scales.len();
+    scales.push((1.0, ""));
+    if !time {
+        scales.extend(LARGE_PREFIXES.iter().rev());
+    }
+
+    // The largest prefix not above the value, moved up when rounding carries
+    // the number to 1000, so `999.6` prints as `1k` rather than `1000`.
+    let magnitude = value.abs();
+    let mut index = if magnitude == 0.0 {
+        unscaled
+    } else {
+        scales.iter().rposition(|(scale, _)| magnitude >= *scale).unwrap_or(0)
+    };
+    let carries = |scale: f64| rounded(value / scale, precision).parse::<f64>().is_ok_and(|v| v.abs() >= 1e3);
+    while index + 1 < scales.len() && carries(scales[index].0) {
+        index += 1;
+    }
+    let (scale, prefix) = scales[index];
+    format!("{}{}{}", trim_number(value / scale, precision), prefix, unit)
+}
+
+/// `value` with `precision` decimals, or as many as three significant digits need.
+fn rounded(value: f64, precision: Option<usize>) -> String {
+    let decimals = precision.unwrap_or(match value.abs() {
+        v if v >= 100.0 => 0,
+        v if v >= 10.0 => 1,
+        _ => 2,
+    });
+    format!("{:.*}", decimals, value)
+}
+
+/// Three significant digits at most, without trailing zeros, or exactly
+/// `precision` decimals when that is set.
+fn trim_number(value: f64, precision: Option<usize>) -> String {
+    let formatted = rounded(value, precision);
+    if precision.is_none() && formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        formatted
+    }
+}
+
+fn format_number_value(value: Option<&number_data_point::Value>, unit: &str, precision: Option<usize>) -> String {
+    match value {
+        Some(number_data_point::Value::AsDouble(v)) => format_value(*v, unit, precision),
+        Some(number_data_point::Value::AsInt(v)) => format_value(*v as f64, unit, precision),
+        None => "-".to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct MetricPoint {
     pub timestamp: u64,
     pub value: f64,
 }
 
+/// Bucket counts of one histogram export, summed over its data points that
+/// share a layout; one column of the TUI heatmap.
+#[derive(Debug, Clone)]
+pub struct BucketColumn {
+    /// Timestamp of the newest merged point, in Unix seconds.
+    pub timestamp: u64,
+    pub bounds: Vec<f64>,
+    pub counts: Vec<u64>,
+    /// Counts accumulate since the start time, so columns must be diffed to
+    /// show what happened between exports.
+    pub cumulative: bool,
+}
+
+impl BucketColumn {
+    /// Merges the points with the same bounds as the first well-formed one;
+    /// points with a different layout are left out of this column.
+    fn merge<'a>(points: impl IntoIterator<Item = &'a HistogramDataPoint>, cumulative: bool) -> Option<Self> {
+        let mut column: Option<Self> = None;
+        for point in points {
+            if point.bucket_counts.len() != point.explicit_bounds.len() + 1 {
+                continue;
+            }
+            let timestamp = MetricsReceiver::point_timestamp(point.time_unix_nano);
+            match &mut column {
+                None => {
+                    column = Some(Self {
+                        timestamp,
+                        bounds: point.explicit_bounds.clone(),
+                        counts: point.bucket_counts.clone(),
+                        cumulative,
+                    })
+                }
+                Some(column) if column.bounds == point.explicit_bounds => {
+                    column.timestamp = column.timestamp.max(timestamp);
+                    for (total, count) in column.counts.iter_mut().zip(&point.bucket_counts) {
+                        *total += count;
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        column
+    }
+}
+
+/// Min, max, mean and latest value over a series' retained points.
+#[derive(Debug, Clone, Copy)]
+pub struct SeriesStats {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub last: f64,
+    /// Timestamp of `last`, in Unix seconds.
+    pub last_timestamp: u64,
+}
+
+impl SeriesStats {
+    /// `None` for a series without finite points.
+    pub fn of<'a>(points: impl IntoIterator<Item = &'a MetricPoint>) -> Option<Self> {
+        let mut stats: Option<Self> = None;
+        let mut sum = 0.0;
+        let mut count = 0usize;
+        for point in points.into_iter().filter(|p| p.value.is_finite()) {
+            sum += point.value;
+            count += 1;
+            let s = stats.get_or_insert(Self {
+                min: point.value,
+                max: point.value,
+                avg: 0.0,
+                last: point.value,
+                last_timestamp: point.timestamp,
+            });
+            s.min = s.min.min(point.value);
+            s.max = s.max.max(point.value);
+            s.last = point.value;
+            s.last_timestamp = point.timestamp;
+        }
+        stats.map(|s| Self { avg: sum / count as f64, ..s })
+    }
+}
+
+/// How a sum's data points relate to each other over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Temporality {
+    Delta,
+    Cumulative,
+    /// Cumulative on the wire, converted to deltas by `--cumulative-to-delta`.
+    CumulativeAsDelta,
+}
+
+impl Temporality {
+    pub fn label(self) -> &'static str {
+        match self {
+            Temporality::Delta => "delta",
+            Temporality::Cumulative => "cumulative",
+            Temporality::CumulativeAsDelta => "cumulative→delta",
+        }
+    }
+}
+
+/// The OTLP data type of a metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    Gauge,
+    Sum,
+    Histogram,
+    ExponentialHistogram,
+    Summary,
+}
+
+impl MetricKind {
+    fn of(data: &Data) -> Self {
+        match data {
+            Data::Gauge(_) => MetricKind::Gauge,
+            Data::Sum(_) => MetricKind::Sum,
+            Data::Histogram(_) => MetricKind::Histogram,
+            Data::ExponentialHistogram(_) => MetricKind::ExponentialHistogram,
+            Data::Summary(_) => MetricKind::Summary,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MetricKind::Gauge => "gauge",
+            MetricKind::Sum => "sum",
+            MetricKind::Histogram => "histogram",
+            MetricKind::ExponentialHistogram => "exponential histogram",
+            MetricKind::Summary => "summary",
+        }
+    }
+}
+
+/// `name@version`, or just `name` when the scope carries no version.
+pub fn format_scope(scope: Option<&InstrumentationScope>) -> Option<String> {
+    let scope = scope.filter(|s| !s.name.is_empty())?;
+    if scope.version.is_empty() {
+        Some(scope.name.clone())
+    } else {
+        Some(format!("{}@{}", scope.name, scope.version))
+    }
+}
+
+/// What a single export request contained, printed by `--one-shot`.
+#[derive(Debug, Clone, Default)]
+pub struct ExportSummary {
+    pub resource_metrics: usize,
+    /// Metric name, kind and number of data points, in request order.
+    pub metrics: Vec<(String, Option<MetricKind>, usize)>,
+}
+
+impl ExportSummary {
+    fn of(request: &ExportMetricsServiceRequest) -> Self {
+        let metrics = request
+            .resource_metrics
+            .iter()
+            .flat_map(|r| &r.scope_metrics)
+            .flat_map(|s| &s.metrics)
+            .map(|metric| {
+                let points = match &metric.data {
+                    Some(Data::Gauge(gauge)) => gauge.data_points.len(),
+                    Some(Data::Sum(sum)) => sum.data_points.len(),
+                    Some(Data::Histogram(histogram)) => histogram.data_points.len(),
+                    Some(Data::ExponentialHistogram(histogram)) => histogram.data_points.len(),
+                    Some(Data::Summary(summary)) => summary.data_points.len(),
+                    None => 0,
+                };
+                (metric.name.clone(), metric.data.as_ref().map(MetricKind::of), points)
+            })
+            .collect();
+        Self {
+            resource_metrics: request.resource_metrics.len(),
+            metrics,
+        }
+    }
+}
+
+impl fmt::Display for ExportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let points: usize = self.metrics.iter().map(|m| m.2).sum();
+        write!(
+            f,
+            "Received {} resource(s), {} metric(s), {} data point(s)",
+            self.resource_metrics,
+            self.metrics.len(),
+            points
+        )?;
+        for (name, kind, points) in &self.metrics {
+            write!(f, "\n  {} ({}): {} point(s)", name, kind.map_or("no data", |k| k.label()), points)?;
+        }
+        Ok(())
+    }
+}
+
+/// Metadata about a metric, sent to the TUI the first time the metric is seen.
+#[derive(Debug, Clone, Default)]
+pub struct MetricInfo {
+    pub description: String,
+    pub unit: String,
+    pub kind: Option<MetricKind>,
+    pub temporality: Option<Temporality>,
+    /// A sum's `is_monotonic`: counters only go up, up-down counters don't.
+    /// `None` for every other type.
+    pub monotonic: Option<bool>,
+    /// Instrumentation scope that produced the metric.
+    pub scope: Option<String>,
+    /// A sum's `start_time_unix_nano` on its first data point, in Unix seconds,
+    /// so the first point already has a rate. `None` when unset, and for
+    /// every other type.
+    pub start_time: Option<u64>,
+}
+
+/// Receiver-side counters reported to the TUI once per second.
+#[derive(Debug, Clone, Default)]
+pub struct ReceiverStats {
+    pub requests_per_second: u64,
+    /// NaN or infinite values dropped since startup.
+    pub nonfinite_values: u64,
+    /// Export requests without a single metric (e.g. keepalives) since startup.
+    pub empty_requests: u64,
+    /// Data points skipped by `--dedup-batch` since startup.
+    pub duplicate_points: u64,
+    /// The `--rate-limit` in requests per second and its counts, when set.
+    pub rate_limit: Option<(u64, RateLimitCounts)>,
+}
+
+/// Totals since startup, exported by `--self-metrics`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReceiverCounters {
+    pub requests: u64,
+    pub dropped_messages: u64,
+    pub discovered_metrics: u64,
+}
+
 #[derive(Debug)]
 pub enum UiMessage {
     NewMetric(String),
-    MetricUpdate(String),
-    MetricDataPoint { 
-        name: String, 
-        point: MetricPoint 
+    MetricInfo {
+        name: String,
+        info: MetricInfo,
+    },
+    /// An attribute set seen for the first time on this metric.
+    MetricAttributes {
+        name: String,
+        attributes: String,
+    },
+    MetricUpdate {
+        name: String,
+        update: String,
+        /// Peer that sent the export; `None` for Unix sockets and in-process requests.
+        source: Option<SocketAddr>,
+        /// Local gRPC address the export arrived on, telling several `--address`
+        /// listeners apart; `None` for Unix sockets, OTLP/HTTP and in-process requests.
+        listener: Option<SocketAddr>,
+    },
+    /// Every data point of one export request, sent together so a large request
+    /// costs one channel message rather than one per point.
+    MetricDataPointBatch(Vec<(String, MetricPoint)>),
+    /// Bucket layout of one histogram export, for the heatmap.
+    HistogramBuckets {
+        name: String,
+        column: BucketColumn,
     },
+    /// A metric that went `--stale-timeout` without data arrived again, e.g.
+    /// after its exporter restarted.
+    MetricReappeared {
+        name: String,
+        gap: Duration,
+    },
+    Stats(ReceiverStats),
+}
+
+/// Requests sent from the TUI back to the receiver.
+#[derive(Debug)]
+pub enum ControlMessage {
+    ClearSeenMetrics,
+}
+
+/// Behaviour switches for the receiver, set from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct ReceiverOptions {
+    pub debug_mode: bool,
+    /// Plot cumulative sums as the difference between consecutive points.
+    pub cumulative_to_delta: bool,
+    /// Only ingest metrics whose name matches this regex.
+    pub include: Option<String>,
+    /// Drop metrics whose name matches this regex, even if `include` matches.
+    pub exclude: Option<String>,
+    /// Append every raw request to this file as length-delimited protobuf.
+    pub capture: Option<std::path::PathBuf>,
+    /// Reject requests beyond this many per second.
+    pub rate_limit: Option<u64>,
+    /// Fraction of requests, between 0 and 1, failed on purpose with `UNAVAILABLE`.
+    pub fail_rate: f64,
+    /// Inline each point's attributes in its update line.
+    pub flatten_attributes: bool,
+    /// Downstream OTLP gRPC endpoint every accepted request is re-exported to.
+    pub forward: Option<String>,
+    /// Fixed decimals for values in update lines; three significant digits when unset.
+    pub precision: Option<usize>,
+    /// File or `udp://host:port` every data point is written to as InfluxDB line protocol.
+    pub influx_out: Option<String>,
+    /// A metric arriving after this long without data is reported as reappeared.
+    pub stale_timeout: Option<Duration>,
+    /// Skip data points repeated within one request.
+    pub dedup_batch: bool,
+}
+
+/// What `--dedup-batch` compares: series, encoded attributes, timestamp and
+/// value bits.
+type PointKey = (String, Vec<Vec<u8>>, u64, Vec<u64>);
+
+/// The parts of a data point that make it a repeat for `--dedup-batch`.
+/// Start times, flags and exemplars are left out, and values compare by their
+/// bits so NaN repeats match too.
+trait DedupKey {
+    fn attributes(&self) -> &[KeyValue];
+    fn timestamp(&self) -> u64;
+    fn value_bits(&self) -> Vec<u64>;
+}
+
+impl DedupKey for NumberDataPoint {
+    fn attributes(&self) -> &[KeyValue] {
+        &self.attributes
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.time_unix_nano
+    }
+
+    fn value_bits(&self) -> Vec<u64> {
+        match self.value {
+            Some(number_data_point::Value::AsDouble(v)) => vec![0, v.to_bits()],
+            Some(number_data_point::Value::AsInt(v)) => vec![1, v as u64],
+            None => Vec::new(),
+        }
+    }
+}
+
+impl DedupKey for HistogramDataPoint {
+    fn attributes(&self) -> &[KeyValue] {
+        &self.attributes
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.time_unix_nano
+    }
+
+    fn value_bits(&self) -> Vec<u64> {
+        let mut bits = vec![self.count, self.sum.map_or(u64::MAX, f64::to_bits), self.explicit_bounds.len() as u64];
+        bits.extend(self.explicit_bounds.iter().map(|b| b.to_bits()));
+        bits.extend(&self.bucket_counts);
+        bits
+    }
+}
+
+impl DedupKey for ExponentialHistogramDataPoint {
+    fn attributes(&self) -> &[KeyValue] {
+        &self.attributes
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.time_unix_nano
+    }
+
+    fn value_bits(&self) -> Vec<u64> {
+        let mut bits = vec![
+            self.count,
+            self.sum.map_or(u64::MAX, f64::to_bits),
+            self.scale as u64,
+            self.zero_count,
+        ];
+        for buckets in [&self.positive, &self.negative] {
+            let buckets = buckets.as_ref().map_or(&[][..], |b| &b.bucket_counts);
+            bits.push(buckets.len() as u64);
+            bits.extend(buckets);
+        }
+        bits.extend([&self.positive, &self.negative].map(|b| b.as_ref().map_or(0, |b| b.offset as u64)));
+        bits
+    }
+}
+
+impl DedupKey for SummaryDataPoint {
+    fn attributes(&self) -> &[KeyValue] {
+        &self.attributes
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.time_unix_nano
+    }
+
+    fn value_bits(&self) -> Vec<u64> {
+        let mut bits = vec![self.count, self.sum.to_bits()];
+        bits.extend(self.quantile_values.iter().flat_map(|q| [q.quantile.to_bits(), q.value.to_bits()]));
+        bits
+    }
+}
+
+/// Where an export came from, carried into its update lines.
+#[derive(Debug, Clone, Copy)]
+struct Origin {
+    source: Option<SocketAddr>,
+    listener: Option<SocketAddr>,
 }
 
 pub struct MetricsReceiver {
-    seen_metrics: TokioMutex<HashSet<String>>,
+    // These are locked per metric rather than for a whole request, and never
+    // across an `.await`, so concurrent exports don't queue behind each other.
+    seen_metrics: Arc<StdMutex<HashSet<String>>>,
+    /// Scope that first emitted each metric name; see `series_key`.
+    metric_scopes: Arc<StdMutex<HashMap<String, String>>>,
+    /// Last cumulative value per series, used by `--cumulative-to-delta`.
+    last_cumulative: Arc<StdMutex<HashMap<String, f64>>>,
+    /// Distinct formatted attribute sets seen per metric.
+    attribute_sets: Arc<StdMutex<HashMap<String, HashSet<String>>>>,
+    /// When each metric last arrived, for `stale_timeout`.
+    last_seen: Arc<StdMutex<HashMap<String, Instant>>>,
+    stale_timeout: Option<Duration>,
+    dedup_batch: bool,
     debug_mode: bool,
+    cumulative_to_delta: bool,
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+    capture: Option<CaptureWriter>,
+    forwarder: Option<Forwarder>,
+    influx: Option<InfluxWriter>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    fail_rate: f64,
+    flatten_attributes: bool,
+    precision: Option<usize>,
     ui_tx: UnboundedSender<UiMessage>,
+    request_count: Arc<AtomicU64>,
+    dropped_count: AtomicU64,
+    nonfinite_count: Arc<AtomicU64>,
+    empty_count: Arc<AtomicU64>,
+    duplicate_count: Arc<AtomicU64>,
+    /// Fired once, after the first export is processed, for `--one-shot`.
+    first_export: StdMutex<Option<oneshot::Sender<ExportSummary>>>,
 }
 
 impl MetricsReceiver {
-    pub fn new(debug_mode: bool, ui_tx: UnboundedSender<UiMessage>) -> Self {
-        Self {
-            seen_metrics: TokioMutex::new(HashSet::new()),
-            debug_mode,
+    pub fn new(options: ReceiverOptions, ui_tx: UnboundedSender<UiMessage>) -> Result<Self, DashboardError> {
+        if !(0.0..=1.0).contains(&options.fail_rate) {
+            return Err(DashboardError::Config(format!(
+                "fail rate must be between 0.0 and 1.0, got {}",
+                options.fail_rate
+            )));
+        }
+        Ok(Self {
+            seen_metrics: Arc::new(StdMutex::new(HashSet::new())),
+            metric_scopes: Arc::new(StdMutex::new(HashMap::new())),
+            last_cumulative: Arc::new(StdMutex::new(HashMap::new())),
+            attribute_sets: Arc::new(StdMutex::new(HashMap::new())),
+            last_seen: Arc::new(StdMutex::new(HashMap::new())),
+            stale_timeout: options.stale_timeout,
+            dedup_batch: options.dedup_batch,
+            debug_mode: options.debug_mode,
+            cumulative_to_delta: options.cumulative_to_delta,
+            include: options.include.as_deref().map(Regex::new).transpose()?,
+            exclude: options.exclude.as_deref().map(Regex::new).transpose()?,
+            capture: options.capture.as_deref().map(CaptureWriter::open).transpose()?,
+            forwarder: options.forward.as_deref().map(Forwarder::start).transpose()?,
+            influx: options.influx_out.as_deref().map(InfluxWriter::open).transpose()?,
+            rate_limiter: options.rate_limit.map(|limit| Arc::new(RateLimiter::new(limit))),
+            fail_rate: options.fail_rate,
+            flatten_attributes: options.flatten_attributes,
+            precision: options.precision,
             ui_tx,
+            request_count: Arc::new(AtomicU64::new(0)),
+            dropped_count: AtomicU64::new(0),
+            nonfinite_count: Arc::new(AtomicU64::new(0)),
+            empty_count: Arc::new(AtomicU64::new(0)),
+            duplicate_count: Arc::new(AtomicU64::new(0)),
+            first_export: StdMutex::new(None),
+        })
+    }
+
+    /// Resolves with a summary of the next export once it has been processed.
+    pub fn first_export(&self) -> oneshot::Receiver<ExportSummary> {
+        let (tx, rx) = oneshot::channel();
+        *self.first_export.lock().unwrap_or_else(|e| e.into_inner()) = Some(tx);
+        rx
+    }
+
+    /// Flushes and closes anything that must survive the process exiting.
+    pub async fn shutdown(&self) {
+        if let Some(capture) = &self.capture {
+            capture.close().await;
+        }
+        if let Some(influx) = &self.influx {
+            influx.close().await;
+        }
+    }
+
+    /// Whether a metric passes the `--include`/`--exclude` filters.
+    fn accepts(&self, metric_name: &str) -> bool {
+        self.include.as_ref().is_none_or(|re| re.is_match(metric_name))
+            && !self.exclude.as_ref().is_some_and(|re| re.is_match(metric_name))
+    }
+
+    /// Reports the export rate to the TUI every second until the channel closes.
+    fn spawn_stats_reporter(&self) {
+        let request_count = Arc::clone(&self.request_count);
+        let nonfinite_count = Arc::clone(&self.nonfinite_count);
+        let empty_count = Arc::clone(&self.empty_count);
+        let duplicate_count = Arc::clone(&self.duplicate_count);
+        let rate_limiter = self.rate_limiter.clone();
+        let ui_tx = self.ui_tx.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            let mut last_count = 0;
+            loop {
+                interval.tick().await;
+                let count = request_count.load(Ordering::Relaxed);
+                let stats = ReceiverStats {
+                    requests_per_second: count - last_count,
+                    nonfinite_values: nonfinite_count.load(Ordering::Relaxed),
+                    empty_requests: empty_count.load(Ordering::Relaxed),
+                    duplicate_points: duplicate_count.load(Ordering::Relaxed),
+                    rate_limit: rate_limiter.as_ref().map(|l| (l.per_second(), l.counts())),
+                };
+                last_count = count;
+                if ui_tx.send(UiMessage::Stats(stats)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// The name a metric's series is stored and listed under. The first scope to
+    /// emit a name keeps it as is; the same name from another scope becomes
+    /// `name (scope)`, so unrelated instruments never share a series.
+    fn series_key(
+        metric_scopes: &mut HashMap<String, String>,
+        metric_name: &str,
+        scope: Option<&InstrumentationScope>,
+    ) -> String {
+        let scope_name = scope.map_or("", |s| s.name.as_str());
+        let owner = metric_scopes
+            .entry(metric_name.to_string())
+            .or_insert_with(|| scope_name.to_string());
+        if owner == scope_name {
+            metric_name.to_string()
+        } else if scope_name.is_empty() {
+            format!("{} (unscoped)", metric_name)
+        } else {
+            format!("{} ({})", metric_name, scope_name)
+        }
+    }
+
+    /// Applies control messages from the TUI until the sending side is dropped.
+    fn spawn_control_listener(&self, mut control_rx: UnboundedReceiver<ControlMessage>) {
+        let seen_metrics = Arc::clone(&self.seen_metrics);
+        let metric_scopes = Arc::clone(&self.metric_scopes);
+        let last_cumulative = Arc::clone(&self.last_cumulative);
+        let attribute_sets = Arc::clone(&self.attribute_sets);
+        let last_seen = Arc::clone(&self.last_seen);
+
+        tokio::spawn(async move {
+            while let Some(message) = control_rx.recv().await {
+                match message {
+                    ControlMessage::ClearSeenMetrics => {
+                        lock(&seen_metrics).clear();
+                        lock(&metric_scopes).clear();
+                        lock(&last_cumulative).clear();
+                        lock(&attribute_sets).clear();
+                        lock(&last_seen).clear();
+                    }
+                }
+            }
+        });
+    }
+
+    /// How long `key` went without data, when that reached `--stale-timeout`.
+    /// Records now as its latest arrival either way.
+    fn gap_before(&self, key: &str) -> Option<Duration> {
+        let timeout = self.stale_timeout?;
+        let now = Instant::now();
+        let previous = lock(&self.last_seen).insert(key.to_string(), now)?;
+        let gap = now.duration_since(previous);
+        (gap >= timeout).then_some(gap)
+    }
+
+    /// Whether `point` repeats the attributes, timestamp and value of one
+    /// already seen under `key` in this request, for `--dedup-batch`.
+    /// Repeats are counted; `seen` is `None` when deduplication is off.
+    fn is_repeat<P: DedupKey>(&self, seen: &mut Option<HashSet<PointKey>>, key: &str, point: &P) -> bool {
+        let Some(seen) = seen else {
+            return false;
+        };
+        let attributes = point.attributes().iter().map(Message::encode_to_vec).collect();
+        if seen.insert((key.to_string(), attributes, point.timestamp(), point.value_bits())) {
+            return false;
         }
+        self.duplicate_count.fetch_add(1, Ordering::Relaxed);
+        true
     }
 
     fn get_current_timestamp() -> u64 {
@@ -45,34 +787,240 @@ impl MetricsReceiver {
             .as_secs()
     }
 
-    async fn send_metric_update(&self, metric_name: &str, details: String) {
-        if let Err(e) = self.ui_tx.send(UiMessage::MetricUpdate(
+    /// Sends to the TUI, counting (and logging) messages that could not be delivered.
+    fn send_ui(&self, message: UiMessage, what: &str) {
+        if let Err(e) = self.ui_tx.send(message) {
+            self.dropped_count.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("Failed to send {}: {}", what, e);
+        }
+    }
+
+    /// Snapshot of the receiver's own counters, used by `--self-metrics`.
+    pub fn counters(&self) -> ReceiverCounters {
+        ReceiverCounters {
+            requests: self.request_count.load(Ordering::Relaxed),
+            dropped_messages: self.dropped_count.load(Ordering::Relaxed),
+            discovered_metrics: lock(&self.seen_metrics).len() as u64,
+        }
+    }
+
+    /// Every metric name accepted so far, sorted; used by `--list-duration`.
+    pub fn seen_metric_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = lock(&self.seen_metrics).iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// One line per data point. With `--flatten-attributes` the point's attributes
+    /// are inlined as `name{k=v,...}` so the feed can be grepped by label value.
+    fn send_metric_update(
+        &self,
+        metric_name: &str,
+        attributes: &[KeyValue],
+        details: String,
+        origin: Origin,
+    ) {
+        let update = if self.flatten_attributes {
+            format!("{}{{{}}} {}", metric_name, format_attributes(attributes), details)
+        } else {
             format!("{}: {}", metric_name, details)
-        )) {
-            eprintln!("Failed to send metric update: {}", e);
+        };
+        self.send_ui(
+            UiMessage::MetricUpdate {
+                name: metric_name.to_string(),
+                update,
+                source: origin.source,
+                listener: origin.listener,
+            },
+            "metric update",
+        );
+    }
+
+    /// Converts an OTLP `time_unix_nano` to seconds, using the receive time when unset.
+    fn point_timestamp(time_unix_nano: u64) -> u64 {
+        if time_unix_nano == 0 {
+            Self::get_current_timestamp()
+        } else {
+            time_unix_nano / 1_000_000_000
         }
     }
 
-    async fn send_metric_datapoint(&self, name: String, value: f64) {
+    fn push_datapoint(batch: &mut Vec<(String, MetricPoint)>, name: String, value: f64, time_unix_nano: u64) {
         let point = MetricPoint {
-            timestamp: Self::get_current_timestamp(),
+            timestamp: Self::point_timestamp(time_unix_nano),
             value,
         };
 
-        if let Err(e) = self.ui_tx.send(UiMessage::MetricDataPoint { 
-            name, 
-            point,
-        }) {
-            eprintln!("Failed to send metric datapoint: {}", e);
+        batch.push((name, point));
+    }
+
+    /// `value` is only formatted in `--debug` mode, keeping it off the hot path.
+    fn debug_point(&self, metric_name: &str, kind: &str, attributes: &[KeyValue], value: impl FnOnce() -> String) {
+        if self.debug_mode {
+            tracing::debug!(
+                metric = metric_name,
+                kind,
+                attributes = %format_attributes(attributes),
+                value = %value(),
+                "decoded data point"
+            );
+        }
+    }
+
+    fn sum_temporality(&self, sum: &Sum) -> Option<Temporality> {
+        match AggregationTemporality::try_from(sum.aggregation_temporality) {
+            Ok(AggregationTemporality::Delta) => Some(Temporality::Delta),
+            Ok(AggregationTemporality::Cumulative) if self.cumulative_to_delta => {
+                Some(Temporality::CumulativeAsDelta)
+            }
+            Ok(AggregationTemporality::Cumulative) => Some(Temporality::Cumulative),
+            _ => None,
+        }
+    }
+
+    fn metric_info(&self, metric: &Metric, scope: Option<&InstrumentationScope>) -> MetricInfo {
+        let (temporality, monotonic, start_time) = match &metric.data {
+            Some(Data::Sum(sum)) => {
+                let start_time = sum
+                    .data_points
+                    .iter()
+                    .find(|p| !Self::no_recorded_value(p.flags))
+                    .map(|p| p.start_time_unix_nano)
+                    .filter(|&start| start > 0)
+                    .map(|start| start / 1_000_000_000);
+                (self.sum_temporality(sum), Some(sum.is_monotonic), start_time)
+            }
+            _ => (None, None, None),
+        };
+        MetricInfo {
+            description: metric.description.clone(),
+            unit: metric.unit.clone(),
+            kind: metric.data.as_ref().map(MetricKind::of),
+            temporality,
+            monotonic,
+            scope: format_scope(scope),
+            start_time,
+        }
+    }
+
+    /// Reports an attribute set to the TUI the first time it shows up on a metric.
+    fn record_attributes(
+        &self,
+        attribute_sets: &mut HashMap<String, HashSet<String>>,
+        metric_name: &str,
+        attributes: &[KeyValue],
+    ) {
+        let formatted = format_attributes(attributes);
+        // Most points repeat a known set, so look up before allocating map keys.
+        if attribute_sets.get(metric_name).is_some_and(|known| known.contains(&formatted)) {
+            return;
+        }
+        let known = attribute_sets.entry(metric_name.to_string()).or_default();
+        if known.insert(formatted.clone()) {
+            self.send_ui(
+                UiMessage::MetricAttributes { name: metric_name.to_string(), attributes: formatted },
+                "metric attributes",
+            );
+        }
+    }
+
+    /// Turns a cumulative value into the increase since the previous point of the
+    /// same series. Returns `None` for the first point, and treats a drop as a
+    /// counter reset.
+    fn cumulative_delta(last_cumulative: &mut HashMap<String, f64>, series: String, value: f64) -> Option<f64> {
+        match last_cumulative.insert(series, value) {
+            Some(previous) if value >= previous => Some(value - previous),
+            Some(_) => Some(value),
+            None => None,
+        }
+    }
+
+    /// Whether a data point is a staleness marker rather than a measurement.
+    /// Such points are left out of the series, which shows as a gap.
+    fn no_recorded_value(flags: u32) -> bool {
+        flags & DataPointFlags::NoRecordedValueMask as u32 != 0
+    }
+
+    /// Passes finite values through; NaN and infinities are counted and dropped
+    /// so they never reach the chart.
+    fn finite(&self, value: f64) -> Option<f64> {
+        if value.is_finite() {
+            Some(value)
+        } else {
+            self.nonfinite_count.fetch_add(1, Ordering::Relaxed);
+            None
         }
     }
 
-    fn extract_value(value: &opentelemetry_proto::tonic::metrics::v1::number_data_point::Value) -> Option<f64> {
+    fn extract_value(&self, value: &opentelemetry_proto::tonic::metrics::v1::number_data_point::Value) -> Option<f64> {
         match value {
-            opentelemetry_proto::tonic::metrics::v1::number_data_point::Value::AsDouble(v) => Some(*v),
+            opentelemetry_proto::tonic::metrics::v1::number_data_point::Value::AsDouble(v) => self.finite(*v),
             opentelemetry_proto::tonic::metrics::v1::number_data_point::Value::AsInt(v) => Some(*v as f64),
         }
     }
+
+    /// Approximates the `q` quantile of a histogram by linear interpolation inside
+    /// the bucket holding the target rank. The open-ended first and last buckets
+    /// are bounded by the point's `min`/`max` when present.
+    fn estimate_quantile(point: &HistogramDataPoint, q: f64) -> Option<f64> {
+        let total: u64 = point.bucket_counts.iter().sum();
+        if total == 0 || point.bucket_counts.len() != point.explicit_bounds.len() + 1 {
+            return None;
+        }
+
+        let rank = q * total as f64;
+        let mut cumulative = 0u64;
+        for (i, &count) in point.bucket_counts.iter().enumerate() {
+            if count == 0 || ((cumulative + count) as f64) < rank {
+                cumulative += count;
+                continue;
+            }
+
+            let lower = match i {
+                0 => point.min.unwrap_or_else(|| point.explicit_bounds.first().copied().unwrap_or(0.0).min(0.0)),
+                _ => point.explicit_bounds[i - 1],
+            };
+            let upper = point
+                .explicit_bounds
+                .get(i)
+                .copied()
+                .unwrap_or_else(|| point.max.unwrap_or(lower));
+            let fraction = (rank - cumulative as f64) / count as f64;
+            return Some(lower + (upper - lower) * fraction.clamp(0.0, 1.0));
+        }
+
+        None
+    }
+}
+
+/// Logs when an export is abandoned because the client went away mid-request.
+///
+/// Hyper drops the handler future when the connection closes, so the only place to
+/// notice is `Drop`. This is routine for exporters that reconnect, not an error.
+struct DisconnectGuard {
+    peer: Option<SocketAddr>,
+    completed: bool,
+}
+
+impl DisconnectGuard {
+    fn new(peer: Option<SocketAddr>) -> Self {
+        Self { peer, completed: false }
+    }
+
+    fn complete(mut self) {
+        self.completed = true;
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        if !self.completed {
+            match self.peer {
+                Some(peer) => tracing::debug!(%peer, "client disconnected before export completed"),
+                None => tracing::debug!("client disconnected before export completed"),
+            }
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -81,64 +1029,584 @@ impl MetricsService for MetricsReceiver {
         &self,
         request: Request<ExportMetricsServiceRequest>,
     ) -> Result<Response<ExportMetricsServiceResponse>, Status> {
+        if let Some(limiter) = &self.rate_limiter {
+            if !limiter.try_acquire() {
+                return Err(Status::resource_exhausted(format!(
+                    "rate limit of {} requests per second exceeded",
+                    limiter.per_second()
+                )));
+            }
+        }
+        // Rejected requests are in the limiter's own counts, not the request rate.
+        self.request_count.fetch_add(1, Ordering::Relaxed);
+        let source = request.remote_addr();
+        let origin = Origin { source, listener: request.local_addr() };
+        if self.fail_rate > 0.0 && rand::random::<f64>() < self.fail_rate {
+            match source {
+                Some(peer) => tracing::info!(%peer, "Injected an export failure (--fail-rate)"),
+                None => tracing::info!("Injected an export failure (--fail-rate)"),
+            }
+            return Err(Status::unavailable("injected failure (--fail-rate)"));
+        }
+        let guard = DisconnectGuard::new(source);
         let metrics = request.into_inner();
-        let mut seen_metrics = self.seen_metrics.lock().await;
-        
+        let first_export = self.first_export.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let summary = first_export.as_ref().map(|_| ExportSummary::of(&metrics));
+        if let Some(capture) = &self.capture {
+            capture.record(metrics.encode_length_delimited_to_vec());
+        }
+        if let Some(forwarder) = &self.forwarder {
+            forwarder.forward(metrics.clone());
+        }
+        // A request with nothing in it is valid, e.g. an exporter's keepalive.
+        let is_empty = metrics
+            .resource_metrics
+            .iter()
+            .flat_map(|r| &r.scope_metrics)
+            .all(|s| s.metrics.is_empty());
+        if is_empty {
+            self.empty_count.fetch_add(1, Ordering::Relaxed);
+            match source {
+                Some(peer) => tracing::debug!(%peer, "empty export request"),
+                None => tracing::debug!("empty export request"),
+            }
+        }
+        let mut batch = Vec::new();
+        let mut influx_lines = Vec::new();
+        let mut seen_points = self.dedup_batch.then(HashSet::new);
         for resource_metrics in metrics.resource_metrics {
             for scope_metrics in &resource_metrics.scope_metrics {
                 for metric in &scope_metrics.metrics {
-                    if seen_metrics.insert(metric.name.clone()) {
-                        if let Err(e) = self.ui_tx.send(UiMessage::NewMetric(metric.name.clone())) {
-                            eprintln!("Failed to send new metric: {}", e);
-                        }
+                    if !self.accepts(&metric.name) {
+                        continue;
+                    }
+                    let key = Self::series_key(&mut lock(&self.metric_scopes), &metric.name, scope_metrics.scope.as_ref());
+
+                    if lock(&self.seen_metrics).insert(key.clone()) {
+                        self.send_ui(UiMessage::NewMetric(key.clone()), "new metric");
+                        let info = self.metric_info(metric, scope_metrics.scope.as_ref());
+                        self.send_ui(UiMessage::MetricInfo { name: key.clone(), info }, "metric info");
+                    }
+                    if let Some(gap) = self.gap_before(&key) {
+                        tracing::info!(metric = %key, "Metric reappeared after {}s without data", gap.as_secs());
+                        self.send_ui(UiMessage::MetricReappeared { name: key.clone(), gap }, "metric reappeared");
                     }
                     
-                    match &metric.data {
-                        Some(data) => {
-                            match data {
-                                opentelemetry_proto::tonic::metrics::v1::metric::Data::Gauge(gauge) => {
-                                    for point in &gauge.data_points {
-                                        if let Some(value) = point.value.as_ref().and_then(Self::extract_value) {
-                                            self.send_metric_datapoint(metric.name.clone(), value).await;
+                    if let Some(data) = &metric.data {
+                        let mut attribute_sets = lock(&self.attribute_sets);
+                        match data {
+                            Data::Gauge(gauge) => {
+                                for point in &gauge.data_points {
+                                    if self.is_repeat(&mut seen_points, &key, point) {
+                                        continue;
+                                    }
+                                    if self.influx.is_some() {
+                                        influx::number_point(&metric.name, point, &mut influx_lines);
+                                    }
+                                    self.debug_point(&key, "gauge", &point.attributes, || format!("{:?}", point.value));
+                                    self.record_attributes(&mut attribute_sets, &key, &point.attributes);
+                                    if Self::no_recorded_value(point.flags) {
+                                        self.send_metric_update(&key, &point.attributes, NO_RECORDED_VALUE.to_string(), origin);
+                                        continue;
+                                    }
+                                    if let Some(value) = point.value.as_ref().and_then(|v| self.extract_value(v)) {
+                                        Self::push_datapoint(&mut batch, key.clone(), value, point.time_unix_nano);
+                                    }
+                                    self.send_metric_update(&key, &point.attributes,
+                                        format!("= {}", format_number_value(point.value.as_ref(), &metric.unit, self.precision)),
+                                        origin,
+                                    );
+                                }
+                            },
+                            Data::Sum(sum) => {
+                                let as_delta = self.sum_temporality(sum) == Some(Temporality::CumulativeAsDelta);
+                                let mut last_cumulative = lock(&self.last_cumulative);
+                                for point in &sum.data_points {
+                                    if self.is_repeat(&mut seen_points, &key, point) {
+                                        continue;
+                                    }
+                                    if self.influx.is_some() {
+                                        influx::number_point(&metric.name, point, &mut influx_lines);
+                                    }
+                                    self.debug_point(&key, "sum", &point.attributes, || format!("{:?}", point.value));
+                                    self.record_attributes(&mut attribute_sets, &key, &point.attributes);
+                                    let series = || format!("{}{{{}}}", key, format_attributes(&point.attributes));
+                                    if Self::no_recorded_value(point.flags) {
+                                        if as_delta {
+                                            // The series restarts after a staleness marker; don't diff across it.
+                                            last_cumulative.remove(&series());
                                         }
-                                        self.send_metric_update(&metric.name, 
-                                            format!("= {:?}", point.value)
-                                        ).await;
-                                    }
-                                },
-                                opentelemetry_proto::tonic::metrics::v1::metric::Data::Sum(sum) => {
-                                    for point in &sum.data_points {
-                                        if let Some(value) = point.value.as_ref().and_then(Self::extract_value) {
-                                            self.send_metric_datapoint(metric.name.clone(), value).await;
+                                        self.send_metric_update(&key, &point.attributes, NO_RECORDED_VALUE.to_string(), origin);
+                                        continue;
+                                    }
+                                    let mut value = point.value.as_ref().and_then(|v| self.extract_value(v));
+                                    if as_delta {
+                                        let series = series();
+                                        value = value.and_then(|v| Self::cumulative_delta(&mut last_cumulative, series, v));
+                                    }
+                                    if let Some(value) = value {
+                                        Self::push_datapoint(&mut batch, key.clone(), value, point.time_unix_nano);
+                                    }
+                                    self.send_metric_update(&key, &point.attributes,
+                                        format!("= {}", format_number_value(point.value.as_ref(), &metric.unit, self.precision)),
+                                        origin,
+                                    );
+                                }
+                            },
+                            Data::Histogram(hist) => {
+                                let unique: Vec<_> = hist
+                                    .data_points
+                                    .iter()
+                                    .filter(|p| !self.is_repeat(&mut seen_points, &key, *p))
+                                    .collect();
+                                for &point in &unique {
+                                    if self.influx.is_some() {
+                                        influx::histogram_point(&metric.name, point, &mut influx_lines);
+                                    }
+                                    self.debug_point(
+                                        &key,
+                                        "histogram",
+                                        &point.attributes,
+                                        || format!("count={} sum={:?} buckets={:?}", point.count, point.sum, point.bucket_counts),
+                                    );
+                                    self.record_attributes(&mut attribute_sets, &key, &point.attributes);
+                                    if Self::no_recorded_value(point.flags) {
+                                        self.send_metric_update(&key, &point.attributes, NO_RECORDED_VALUE.to_string(), origin);
+                                        continue;
+                                    }
+                                    if let Some(sum) = point.sum.and_then(|v| self.finite(v)) {
+                                        Self::push_datapoint(&mut batch, key.clone(), sum, point.time_unix_nano);
+                                    }
+                                    for (q, label) in HISTOGRAM_QUANTILES {
+                                        if let Some(value) = Self::estimate_quantile(point, q).and_then(|v| self.finite(v)) {
+                                            Self::push_datapoint(
+                                                &mut batch,
+                                                quantile_series_name(&key, label),
+                                                value,
+                                                point.time_unix_nano,
+                                            );
                                         }
-                                        self.send_metric_update(&metric.name, 
-                                            format!("= {:?}", point.value)
-                                        ).await;
-                                    }
-                                },
-                                opentelemetry_proto::tonic::metrics::v1::metric::Data::Histogram(hist) => {
-                                    for point in &hist.data_points {
-                                        if let Some(sum) = point.sum {
-                                            self.send_metric_datapoint(metric.name.clone(), sum).await;
+                                    }
+                                    self.send_metric_update(&key, &point.attributes,
+                                        format!(
+                                            "count: {}, sum: {}",
+                                            point.count,
+                                            point.sum.map_or("-".to_string(), |sum| format_value(sum, &metric.unit, self.precision))
+                                        ),
+                                        origin,
+                                    );
+                                }
+                                let cumulative = hist.aggregation_temporality == AggregationTemporality::Cumulative as i32;
+                                let points = unique.into_iter().filter(|p| !Self::no_recorded_value(p.flags));
+                                if let Some(column) = BucketColumn::merge(points, cumulative) {
+                                    self.send_ui(UiMessage::HistogramBuckets { name: key.clone(), column }, "histogram buckets");
+                                }
+                            },
+                            Data::Summary(summary) => {
+                                for point in &summary.data_points {
+                                    if self.is_repeat(&mut seen_points, &key, point) {
+                                        continue;
+                                    }
+                                    if self.influx.is_some() {
+                                        influx::summary_point(&metric.name, point, &mut influx_lines);
+                                    }
+                                    self.debug_point(
+                                        &key,
+                                        "summary",
+                                        &point.attributes,
+                                        || format!("count={} sum={} quantiles={:?}", point.count, point.sum, point.quantile_values),
+                                    );
+                                    self.record_attributes(&mut attribute_sets, &key, &point.attributes);
+                                    if Self::no_recorded_value(point.flags) {
+                                        self.send_metric_update(&key, &point.attributes, NO_RECORDED_VALUE.to_string(), origin);
+                                        continue;
+                                    }
+                                    if let Some(sum) = self.finite(point.sum) {
+                                        Self::push_datapoint(&mut batch, key.clone(), sum, point.time_unix_nano);
+                                    }
+                                    let mut details = format!(
+                                        "count: {}, sum: {}",
+                                        point.count,
+                                        format_value(point.sum, &metric.unit, self.precision)
+                                    );
+                                    for quantile in &point.quantile_values {
+                                        let label = quantile_label(quantile.quantile);
+                                        details.push_str(&format!(", {}: {}", label, format_value(quantile.value, &metric.unit, self.precision)));
+                                        if let Some(value) = self.finite(quantile.value) {
+                                            Self::push_datapoint(
+                                                &mut batch,
+                                                quantile_series_name(&key, &label),
+                                                value,
+                                                point.time_unix_nano,
+                                            );
                                         }
-                                        self.send_metric_update(&metric.name, 
-                                            format!("count: {}, sum: {:?}", point.count, point.sum)
-                                        ).await;
                                     }
-                                },
-                                _ => {}
+                                    self.send_metric_update(&key, &point.attributes, details, origin);
+                                }
+                            },
+                            Data::ExponentialHistogram(hist) => {
+                                // Not graphed, but still written to `--influx-out`.
+                                for point in &hist.data_points {
+                                    if !self.is_repeat(&mut seen_points, &key, point) && self.influx.is_some() {
+                                        influx::exponential_histogram_point(&metric.name, point, &mut influx_lines);
+                                    }
+                                }
+                                if self.debug_mode {
+                                    tracing::debug!(metric = %metric.name, data = ?data, "unsupported metric type");
+                                }
                             }
-                        },
-                        None => {}
+                        }
                     }
                 }
             }
         }
 
+        if !batch.is_empty() {
+            self.send_ui(UiMessage::MetricDataPointBatch(batch), "metric datapoints");
+        }
+        if let Some(influx) = &self.influx {
+            influx.write(influx_lines);
+        }
+        guard.complete();
+        if let (Some(tx), Some(summary)) = (first_export, summary) {
+            let _ = tx.send(summary);
+        }
         Ok(Response::new(ExportMetricsServiceResponse::default()))
     }
 }
 
-pub fn create_metrics_service(debug_mode: bool, ui_tx: UnboundedSender<UiMessage>) -> MetricsServiceServer<MetricsReceiver> {
-    MetricsServiceServer::new(MetricsReceiver::new(debug_mode, ui_tx))
+/// Builds the shared receiver behind both the gRPC and the HTTP endpoints.
+pub fn create_receiver(
+    options: ReceiverOptions,
+    ui_tx: UnboundedSender<UiMessage>,
+    control_rx: UnboundedReceiver<ControlMessage>,
+) -> Result<Arc<MetricsReceiver>, DashboardError> {
+    let receiver = MetricsReceiver::new(options, ui_tx)?;
+    receiver.spawn_stats_reporter();
+    receiver.spawn_control_listener(control_rx);
+    Ok(Arc::new(receiver))
+}
+
+/// `max_recv_size` overrides tonic's 4MB limit on a decoded request.
+pub fn create_metrics_service(
+    receiver: Arc<MetricsReceiver>,
+    max_recv_size: Option<usize>,
+) -> MetricsServiceServer<MetricsReceiver> {
+    let service = MetricsServiceServer::from_arc(receiver);
+    match max_recv_size {
+        Some(limit) => service.max_decoding_message_size(limit),
+        None => service,
+    }
+}
+
+/// Tonic rejects oversized requests before `export` runs and only tells the
+/// client, so this response hook logs them on our side as well.
+pub fn log_rejected_export(response: http::Response<BoxBody>) -> http::Response<BoxBody> {
+    if let Some(status) = Status::from_header_map(response.headers()) {
+        if status.code() == Code::OutOfRange {
+            tracing::warn!(
+                "Rejected an export: {} (raise the limit with --max-recv-size)",
+                status.message()
+            );
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil;
+    use opentelemetry_proto::tonic::collector::metrics::v1::metrics_service_client::MetricsServiceClient;
+    use std::collections::BTreeSet;
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    /// Everything queued so far; `export` sends before it returns.
+    fn drain(rx: &mut UnboundedReceiver<UiMessage>) -> Vec<UiMessage> {
+        let mut messages = Vec::new();
+        while let Ok(message) = rx.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn announced(messages: &[UiMessage]) -> BTreeSet<String> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                UiMessage::NewMetric(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn plotted(messages: &[UiMessage]) -> Vec<(String, f64)> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                UiMessage::MetricDataPointBatch(points) => Some(points),
+                _ => None,
+            })
+            .flatten()
+            .map(|(name, point)| (name.clone(), point.value))
+            .collect()
+    }
+
+    #[test]
+    fn format_decimal_switches_to_scientific_at_the_thresholds() {
+        let cases = [
+            (1234.5678, 2, "1234.57"),
+            (999_999_999.0, 2, "999999999.00"),
+            (1e9, 2, "1.00e9"),
+            (-2.5e10, 1, "-2.5e10"),
+            (0.01, 2, "0.01"),
+            (0.001, 2, "1.00e-3"),
+            (0.0, 2, "0.00"),
+            (12.7, 0, "13"),
+            (1.0, 0, "1"),
+            (0.4, 0, "4e-1"),
+            (f64::NAN, 2, "NaN"),
+            (f64::INFINITY, 2, "inf"),
+        ];
+        for (value, precision, expected) in cases {
+            assert_eq!(format_decimal(value, precision), expected, "{} with precision {}", value, precision);
+        }
+    }
+
+    #[test]
+    fn format_value_picks_the_prefix_after_rounding() {
+        let cases = [
+            (999.4, "", None, "999"),
+            (999.6, "", None, "1k"),
+            (1234.0, "", None, "1.23k"),
+            (-1500.0, "1", None, "-1.5k"),
+            (999_600.0, "By", None, "1MB"),
+            (1234.0, "", Some(1), "1.2k"),
+            (0.25, "{requests}", None, "0.25"),
+            (0.0045, "s", None, "4.5ms"),
+            (0.9996, "s", None, "1s"),
+            (0.0, "s", None, "0s"),
+            (12.5, "%", None, "12.5%"),
+            (f64::NAN, "", None, "NaN"),
+        ];
+        for (value, unit, precision, expected) in cases {
+            assert_eq!(format_value(value, unit, precision), expected, "{} {:?}", value, unit);
+        }
+    }
+
+    #[test]
+    fn format_value_keeps_time_in_seconds() {
+        let cases = [
+            (4500.0, "ms", "4.5s"),
+            (3e6, "us", "3s"),
+            (250.0, "ns", "250ns"),
+            (4500.0, "s", "4500s"),
+            (2e6, "ms", "2000s"),
+            (90.0, "min", "90min"),
+        ];
+        for (value, unit, expected) in cases {
+            assert_eq!(format_value(value, unit, None), expected, "{} {:?}", value, unit);
+        }
+    }
+
+    #[tokio::test]
+    async fn export_announces_and_plots_each_type() {
+        let (receiver, mut rx) = testutil::in_memory_receiver(ReceiverOptions::default()).unwrap();
+        let request = testutil::request(vec![
+            testutil::gauge("test.gauge", 1.0),
+            testutil::sum("test.sum", 2.0, true),
+            testutil::histogram("test.histogram", &[10.0, 100.0], &[1, 2, 1]),
+        ]);
+        receiver.export(Request::new(request)).await.unwrap();
+        receiver.shutdown().await;
+
+        let messages = drain(&mut rx);
+        let names = announced(&messages);
+        let points = plotted(&messages);
+        for name in ["test.gauge", "test.sum", "test.histogram"] {
+            assert!(names.contains(name), "{} was not announced", name);
+            assert!(points.iter().any(|(series, _)| series.starts_with(name)), "{} has no points", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn same_name_from_two_scopes_stays_apart() {
+        let (receiver, mut rx) = testutil::in_memory_receiver(ReceiverOptions::default()).unwrap();
+        let request = testutil::scoped_request(vec![
+            ("api", vec![testutil::gauge("requests", 1.0)]),
+            ("db", vec![testutil::gauge("requests", 2.0)]),
+        ]);
+        receiver.export(Request::new(request)).await.unwrap();
+        receiver.shutdown().await;
+
+        let messages = drain(&mut rx);
+        let expected: BTreeSet<String> = ["requests", "requests (db)"].map(String::from).into();
+        assert_eq!(announced(&messages), expected);
+        assert_eq!(
+            plotted(&messages),
+            vec![("requests".to_string(), 1.0), ("requests (db)".to_string(), 2.0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn point_without_recorded_value_is_not_plotted() {
+        let (receiver, mut rx) = testutil::in_memory_receiver(ReceiverOptions::default()).unwrap();
+        let mut metric = testutil::gauge("temperature", 21.0);
+        if let Some(Data::Gauge(gauge)) = &mut metric.data {
+            let mut stale = gauge.data_points[0].clone();
+            stale.value = Some(number_data_point::Value::AsDouble(99.0));
+            stale.flags = DataPointFlags::NoRecordedValueMask as u32;
+            gauge.data_points.insert(0, stale);
+        }
+        receiver.export(Request::new(testutil::request(vec![metric]))).await.unwrap();
+        receiver.shutdown().await;
+
+        let messages = drain(&mut rx);
+        assert_eq!(plotted(&messages), vec![("temperature".to_string(), 21.0)]);
+        assert!(messages.iter().any(|message| matches!(
+            message,
+            UiMessage::MetricUpdate { update, .. } if update.contains(NO_RECORDED_VALUE)
+        )));
+    }
+
+    #[tokio::test]
+    async fn empty_request_is_accepted_and_counted() {
+        let (receiver, mut rx) = testutil::in_memory_receiver(ReceiverOptions::default()).unwrap();
+        let empty = ExportMetricsServiceRequest::default();
+        assert!(receiver.export(Request::new(empty)).await.is_ok());
+        let empty_scope = testutil::request(Vec::new());
+        assert!(receiver.export(Request::new(empty_scope)).await.is_ok());
+        receiver.shutdown().await;
+
+        assert!(announced(&drain(&mut rx)).is_empty());
+        assert_eq!(receiver.empty_count.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn dedup_batch_drops_a_repeated_point() {
+        let options = ReceiverOptions {
+            dedup_batch: true,
+            ..Default::default()
+        };
+        let (receiver, mut rx) = testutil::in_memory_receiver(options).unwrap();
+        let mut gauge = testutil::gauge("g", 1.0);
+        let Some(Data::Gauge(points)) = &mut gauge.data else {
+            unreachable!();
+        };
+        // Only a start time differs, which doesn't make the point distinct.
+        let mut repeat = points.data_points[0].clone();
+        repeat.start_time_unix_nano += 1;
+        let mut other = points.data_points[0].clone();
+        other.value = Some(number_data_point::Value::AsDouble(2.0));
+        points.data_points.extend([repeat, other]);
+
+        receiver.export(Request::new(testutil::request(vec![gauge]))).await.unwrap();
+        assert_eq!(plotted(&drain(&mut rx)), vec![("g".to_string(), 1.0), ("g".to_string(), 2.0)]);
+        assert_eq!(receiver.duplicate_count.load(Ordering::Relaxed), 1);
+        receiver.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn dedup_batch_applies_to_influx_lines() {
+        let path = std::env::temp_dir().join(format!("otel-dashboard-dedup-{}.lp", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let options = ReceiverOptions {
+            dedup_batch: true,
+            influx_out: Some(path.display().to_string()),
+            ..Default::default()
+        };
+        let (receiver, _rx) = testutil::in_memory_receiver(options).unwrap();
+        let request = testutil::request(vec![testutil::gauge("g", 1.0), testutil::gauge("g", 1.0)]);
+        receiver.export(Request::new(request)).await.unwrap();
+        receiver.shutdown().await;
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written, "g value=1\n");
+    }
+
+    #[tokio::test]
+    async fn reappearance_is_reported_once_per_gap() {
+        let options = ReceiverOptions {
+            stale_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let (receiver, mut rx) = testutil::in_memory_receiver(options).unwrap();
+        let export = || receiver.export(Request::new(testutil::request(vec![testutil::gauge("g", 1.0)])));
+        let reappeared = |messages: Vec<UiMessage>| {
+            messages
+                .iter()
+                .filter(|message| matches!(message, UiMessage::MetricReappeared { name, .. } if name == "g"))
+                .count()
+        };
+
+        export().await.unwrap();
+        export().await.unwrap();
+        assert_eq!(reappeared(drain(&mut rx)), 0);
+
+        for _ in 0..2 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            export().await.unwrap();
+            export().await.unwrap();
+            assert_eq!(reappeared(drain(&mut rx)), 1);
+        }
+        receiver.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_are_not_counted_as_received() {
+        let options = ReceiverOptions {
+            rate_limit: Some(1),
+            ..Default::default()
+        };
+        let (receiver, _rx) = testutil::in_memory_receiver(options).unwrap();
+        let export = || receiver.export(Request::new(testutil::request(vec![testutil::gauge("g", 1.0)])));
+        export().await.unwrap();
+        assert_eq!(export().await.unwrap_err().code(), Code::ResourceExhausted);
+        assert_eq!(receiver.request_count.load(Ordering::Relaxed), 1);
+        receiver.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn server_keeps_serving_after_a_client_drops_mid_request() {
+        let (receiver, mut rx) = testutil::in_memory_receiver(ReceiverOptions::default()).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(create_metrics_service(receiver.clone(), None))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+
+        // Start an export whose gRPC frame announces 1000 bytes, send a few of
+        // them and drop the connection.
+        let tcp = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let (client, connection) = h2::client::handshake(tcp).await.unwrap();
+        let connection = tokio::spawn(connection);
+        let mut client = client.ready().await.unwrap();
+        let request = http::Request::post(format!(
+            "http://{}/opentelemetry.proto.collector.metrics.v1.MetricsService/Export",
+            addr
+        ))
+        .header("content-type", "application/grpc")
+        .header("te", "trailers")
+        .body(())
+        .unwrap();
+        let (_response, mut stream) = client.send_request(request, false).unwrap();
+        stream
+            .send_data(axum::body::Bytes::from_static(&[0, 0, 0, 3, 232, 10, 2, 18]), false)
+            .unwrap();
+        tokio::task::yield_now().await;
+        drop((client, stream));
+        connection.abort();
+
+        let mut client = MetricsServiceClient::connect(format!("http://{}", addr)).await.unwrap();
+        client
+            .export(testutil::request(vec![testutil::gauge("after.disconnect", 1.0)]))
+            .await
+            .unwrap();
+        server.abort();
+        receiver.shutdown().await;
+        assert_eq!(plotted(&drain(&mut rx)), vec![("after.disconnect".to_string(), 1.0)]);
+    }
 }
@@ -0,0 +1,421 @@
+use base64::Engine;
+use opentelemetry_proto::tonic::collector::metrics::v1::{
+    ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+};
+use opentelemetry_proto::tonic::common::v1::{
+    any_value, AnyValue, ArrayValue, InstrumentationScope, KeyValue, KeyValueList,
+};
+use opentelemetry_proto::tonic::metrics::v1::{
+    exemplar, exponential_histogram_data_point::Buckets, metric::Data, number_data_point,
+    summary_data_point::ValueAtQuantile, AggregationTemporality, Exemplar, ExponentialHistogram,
+    ExponentialHistogramDataPoint, Gauge, Histogram, HistogramDataPoint, Metric, NumberDataPoint,
+    ResourceMetrics, ScopeMetrics, Sum, Summary, SummaryDataPoint,
+};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use serde_json::{json, Map, Value};
+
+type Object = Map<String, Value>;
+type Result<T> = std::result::Result<T, String>;
+
+/// Decodes an OTLP/JSON `ExportMetricsServiceRequest` as the spec defines it,
+/// which is not what the serde derive in `opentelemetry-proto` accepts: keys are
+/// lowerCamelCase (snake_case is taken too), omitted fields take their proto
+/// default, 64-bit integers may be strings, enums are numbers, `oneof`s are
+/// flattened into their parent (`"gauge": {..}`, `"asDouble": 1`), trace and
+/// span ids are hex and other bytes base64.
+pub fn decode_request(body: &[u8]) -> Result<ExportMetricsServiceRequest> {
+    let value: Value = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    let request = object(&value, "request")?;
+    Ok(ExportMetricsServiceRequest {
+        resource_metrics: list(request, "resourceMetrics", resource_metrics)?,
+    })
+}
+
+/// Encodes a response the way the spec does: `{}` on full success.
+pub fn encode_response(response: &ExportMetricsServiceResponse) -> Vec<u8> {
+    let value = match &response.partial_success {
+        Some(partial) => json!({
+            "partialSuccess": {
+                "rejectedDataPoints": partial.rejected_data_points.to_string(),
+                "errorMessage": partial.error_message,
+            }
+        }),
+        None => json!({}),
+    };
+    value.to_string().into_bytes()
+}
+
+fn resource_metrics(value: &Value) -> Result<ResourceMetrics> {
+    let obj = object(value, "resourceMetrics")?;
+    Ok(ResourceMetrics {
+        resource: message(obj, "resource", resource)?,
+        scope_metrics: list(obj, "scopeMetrics", scope_metrics)?,
+        schema_url: string(obj, "schemaUrl")?,
+    })
+}
+
+fn resource(value: &Value) -> Result<Resource> {
+    let obj = object(value, "resource")?;
+    Ok(Resource {
+        attributes: list(obj, "attributes", key_value)?,
+        dropped_attributes_count: uint32(obj, "droppedAttributesCount")?,
+    })
+}
+
+fn scope_metrics(value: &Value) -> Result<ScopeMetrics> {
+    let obj = object(value, "scopeMetrics")?;
+    Ok(ScopeMetrics {
+        scope: message(obj, "scope", scope)?,
+        metrics: list(obj, "metrics", metric)?,
+        schema_url: string(obj, "schemaUrl")?,
+    })
+}
+
+fn scope(value: &Value) -> Result<InstrumentationScope> {
+    let obj = object(value, "scope")?;
+    Ok(InstrumentationScope {
+        name: string(obj, "name")?,
+        version: string(obj, "version")?,
+        attributes: list(obj, "attributes", key_value)?,
+        dropped_attributes_count: uint32(obj, "droppedAttributesCount")?,
+    })
+}
+
+fn metric(value: &Value) -> Result<Metric> {
+    let obj = object(value, "metric")?;
+    let data = if let Some(value) = field(obj, "gauge") {
+        Some(Data::Gauge(gauge(value)?))
+    } else if let Some(value) = field(obj, "sum") {
+        Some(Data::Sum(sum(value)?))
+    } else if let Some(value) = field(obj, "histogram") {
+        Some(Data::Histogram(histogram(value)?))
+    } else if let Some(value) = field(obj, "exponentialHistogram") {
+        Some(Data::ExponentialHistogram(exponential_histogram(value)?))
+    } else if let Some(value) = field(obj, "summary") {
+        Some(Data::Summary(summary(value)?))
+    } else {
+        None
+    };
+    Ok(Metric {
+        name: string(obj, "name")?,
+        description: string(obj, "description")?,
+        unit: string(obj, "unit")?,
+        data,
+    })
+}
+
+fn gauge(value: &Value) -> Result<Gauge> {
+    let obj = object(value, "gauge")?;
+    Ok(Gauge {
+        data_points: list(obj, "dataPoints", number_data_point)?,
+    })
+}
+
+fn sum(value: &Value) -> Result<Sum> {
+    let obj = object(value, "sum")?;
+    Ok(Sum {
+        data_points: list(obj, "dataPoints", number_data_point)?,
+        aggregation_temporality: temporality(obj)?,
+        is_monotonic: boolean(obj, "isMonotonic")?,
+    })
+}
+
+fn histogram(value: &Value) -> Result<Histogram> {
+    let obj = object(value, "histogram")?;
+    Ok(Histogram {
+        data_points: list(obj, "dataPoints", histogram_data_point)?,
+        aggregation_temporality: temporality(obj)?,
+    })
+}
+
+fn exponential_histogram(value: &Value) -> Result<ExponentialHistogram> {
+    let obj = object(value, "exponentialHistogram")?;
+    Ok(ExponentialHistogram {
+        data_points: list(obj, "dataPoints", exponential_histogram_data_point)?,
+        aggregation_temporality: temporality(obj)?,
+    })
+}
+
+fn summary(value: &Value) -> Result<Summary> {
+    let obj = object(value, "summary")?;
+    Ok(Summary {
+        data_points: list(obj, "dataPoints", summary_data_point)?,
+    })
+}
+
+fn number_data_point(value: &Value) -> Result<NumberDataPoint> {
+    let obj = object(value, "dataPoints")?;
+    let value = if field(obj, "asDouble").is_some() {
+        Some(number_data_point::Value::AsDouble(double(obj, "asDouble")?))
+    } else if field(obj, "asInt").is_some() {
+        Some(number_data_point::Value::AsInt(int64(obj, "asInt")?))
+    } else {
+        None
+    };
+    Ok(NumberDataPoint {
+        attributes: list(obj, "attributes", key_value)?,
+        start_time_unix_nano: uint64(obj, "startTimeUnixNano")?,
+        time_unix_nano: uint64(obj, "timeUnixNano")?,
+        exemplars: list(obj, "exemplars", exemplar)?,
+        flags: uint32(obj, "flags")?,
+        value,
+    })
+}
+
+fn histogram_data_point(value: &Value) -> Result<HistogramDataPoint> {
+    let obj = object(value, "dataPoints")?;
+    Ok(HistogramDataPoint {
+        attributes: list(obj, "attributes", key_value)?,
+        start_time_unix_nano: uint64(obj, "startTimeUnixNano")?,
+        time_unix_nano: uint64(obj, "timeUnixNano")?,
+        count: uint64(obj, "count")?,
+        sum: optional_double(obj, "sum")?,
+        bucket_counts: list(obj, "bucketCounts", |value| parse_u64(value, "bucketCounts"))?,
+        explicit_bounds: list(obj, "explicitBounds", |value| parse_f64(value, "explicitBounds"))?,
+        exemplars: list(obj, "exemplars", exemplar)?,
+        flags: uint32(obj, "flags")?,
+        min: optional_double(obj, "min")?,
+        max: optional_double(obj, "max")?,
+    })
+}
+
+fn exponential_histogram_data_point(value: &Value) -> Result<ExponentialHistogramDataPoint> {
+    let obj = object(value, "dataPoints")?;
+    Ok(ExponentialHistogramDataPoint {
+        attributes: list(obj, "attributes", key_value)?,
+        start_time_unix_nano: uint64(obj, "startTimeUnixNano")?,
+        time_unix_nano: uint64(obj, "timeUnixNano")?,
+        count: uint64(obj, "count")?,
+        sum: optional_double(obj, "sum")?,
+        scale: int32(obj, "scale")?,
+        zero_count: uint64(obj, "zeroCount")?,
+        positive: message(obj, "positive", buckets)?,
+        negative: message(obj, "negative", buckets)?,
+        flags: uint32(obj, "flags")?,
+        exemplars: list(obj, "exemplars", exemplar)?,
+        min: optional_double(obj, "min")?,
+        max: optional_double(obj, "max")?,
+        zero_threshold: double(obj, "zeroThreshold")?,
+    })
+}
+
+fn buckets(value: &Value) -> Result<Buckets> {
+    let obj = object(value, "buckets")?;
+    Ok(Buckets {
+        offset: int32(obj, "offset")?,
+        bucket_counts: list(obj, "bucketCounts", |value| parse_u64(value, "bucketCounts"))?,
+    })
+}
+
+fn summary_data_point(value: &Value) -> Result<SummaryDataPoint> {
+    let obj = object(value, "dataPoints")?;
+    Ok(SummaryDataPoint {
+        attributes: list(obj, "attributes", key_value)?,
+        start_time_unix_nano: uint64(obj, "startTimeUnixNano")?,
+        time_unix_nano: uint64(obj, "timeUnixNano")?,
+        count: uint64(obj, "count")?,
+        sum: double(obj, "sum")?,
+        quantile_values: list(obj, "quantileValues", value_at_quantile)?,
+        flags: uint32(obj, "flags")?,
+    })
+}
+
+fn value_at_quantile(value: &Value) -> Result<ValueAtQuantile> {
+    let obj = object(value, "quantileValues")?;
+    Ok(ValueAtQuantile {
+        quantile: double(obj, "quantile")?,
+        value: double(obj, "value")?,
+    })
+}
+
+fn exemplar(value: &Value) -> Result<Exemplar> {
+    let obj = object(value, "exemplars")?;
+    let value = if field(obj, "asDouble").is_some() {
+        Some(exemplar::Value::AsDouble(double(obj, "asDouble")?))
+    } else if field(obj, "asInt").is_some() {
+        Some(exemplar::Value::AsInt(int64(obj, "asInt")?))
+    } else {
+        None
+    };
+    Ok(Exemplar {
+        filtered_attributes: list(obj, "filteredAttributes", key_value)?,
+        time_unix_nano: uint64(obj, "timeUnixNano")?,
+        span_id: hex_bytes(obj, "spanId")?,
+        trace_id: hex_bytes(obj, "traceId")?,
+        value,
+    })
+}
+
+fn key_value(value: &Value) -> Result<KeyValue> {
+    let obj = object(value, "attributes")?;
+    Ok(KeyValue {
+        key: string(obj, "key")?,
+        value: message(obj, "value", any_value)?,
+    })
+}
+
+fn any_value(value: &Value) -> Result<AnyValue> {
+    let obj = object(value, "value")?;
+    let value = if field(obj, "stringValue").is_some() {
+        Some(any_value::Value::StringValue(string(obj, "stringValue")?))
+    } else if field(obj, "boolValue").is_some() {
+        Some(any_value::Value::BoolValue(boolean(obj, "boolValue")?))
+    } else if field(obj, "intValue").is_some() {
+        Some(any_value::Value::IntValue(int64(obj, "intValue")?))
+    } else if field(obj, "doubleValue").is_some() {
+        Some(any_value::Value::DoubleValue(double(obj, "doubleValue")?))
+    } else if let Some(array) = field(obj, "arrayValue") {
+        Some(any_value::Value::ArrayValue(ArrayValue {
+            values: list(object(array, "arrayValue")?, "values", any_value)?,
+        }))
+    } else if let Some(kvlist) = field(obj, "kvlistValue") {
+        Some(any_value::Value::KvlistValue(KeyValueList {
+            values: list(object(kvlist, "kvlistValue")?, "values", key_value)?,
+        }))
+    } else if let Some(bytes) = field(obj, "bytesValue") {
+        let encoded = bytes.as_str().ok_or("bytesValue: expected a base64 string")?;
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| format!("bytesValue: {}", e))?;
+        Some(any_value::Value::BytesValue(decoded))
+    } else {
+        None
+    };
+    Ok(AnyValue { value })
+}
+
+/// `aggregationTemporality` as its enum number, or its name.
+fn temporality(obj: &Object) -> Result<i32> {
+    match field(obj, "aggregationTemporality") {
+        None => Ok(0),
+        Some(Value::String(name)) => AggregationTemporality::from_str_name(name)
+            .map(|temporality| temporality as i32)
+            .ok_or_else(|| format!("aggregationTemporality: unknown value {:?}", name)),
+        Some(value) => parse_i64(value, "aggregationTemporality")
+            .and_then(|n| i32::try_from(n).map_err(|_| "aggregationTemporality: out of range".to_string())),
+    }
+}
+
+fn object<'a>(value: &'a Value, name: &str) -> Result<&'a Object> {
+    value.as_object().ok_or_else(|| format!("{}: expected an object", name))
+}
+
+/// The value of `name`, also looked up under its proto (snake_case) name. A
+/// `null` counts as absent, like an omitted field.
+fn field<'a>(obj: &'a Object, name: &str) -> Option<&'a Value> {
+    obj.get(name)
+        .or_else(|| obj.get(&snake_case(name)))
+        .filter(|value| !value.is_null())
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn message<T>(obj: &Object, name: &str, decode: fn(&Value) -> Result<T>) -> Result<Option<T>> {
+    field(obj, name).map(decode).transpose()
+}
+
+fn list<T>(obj: &Object, name: &str, decode: impl Fn(&Value) -> Result<T>) -> Result<Vec<T>> {
+    match field(obj, name) {
+        None => Ok(Vec::new()),
+        Some(Value::Array(values)) => values.iter().map(decode).collect(),
+        Some(_) => Err(format!("{}: expected an array", name)),
+    }
+}
+
+fn string(obj: &Object, name: &str) -> Result<String> {
+    match field(obj, name) {
+        None => Ok(String::new()),
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(_) => Err(format!("{}: expected a string", name)),
+    }
+}
+
+fn boolean(obj: &Object, name: &str) -> Result<bool> {
+    match field(obj, name) {
+        None => Ok(false),
+        Some(Value::Bool(b)) => Ok(*b),
+        Some(_) => Err(format!("{}: expected a boolean", name)),
+    }
+}
+
+fn uint64(obj: &Object, name: &str) -> Result<u64> {
+    field(obj, name).map_or(Ok(0), |value| parse_u64(value, name))
+}
+
+fn int64(obj: &Object, name: &str) -> Result<i64> {
+    field(obj, name).map_or(Ok(0), |value| parse_i64(value, name))
+}
+
+fn uint32(obj: &Object, name: &str) -> Result<u32> {
+    uint64(obj, name)?
+        .try_into()
+        .map_err(|_| format!("{}: out of range", name))
+}
+
+fn int32(obj: &Object, name: &str) -> Result<i32> {
+    int64(obj, name)?
+        .try_into()
+        .map_err(|_| format!("{}: out of range", name))
+}
+
+fn double(obj: &Object, name: &str) -> Result<f64> {
+    Ok(optional_double(obj, name)?.unwrap_or_default())
+}
+
+fn optional_double(obj: &Object, name: &str) -> Result<Option<f64>> {
+    field(obj, name).map(|value| parse_f64(value, name)).transpose()
+}
+
+fn hex_bytes(obj: &Object, name: &str) -> Result<Vec<u8>> {
+    match field(obj, name) {
+        None => Ok(Vec::new()),
+        Some(Value::String(s)) => hex::decode(s).map_err(|e| format!("{}: {}", name, e)),
+        Some(_) => Err(format!("{}: expected a hex string", name)),
+    }
+}
+
+/// 64-bit integers come as JSON strings per the spec, but plain numbers are
+/// common enough from hand-written payloads to take as well.
+fn parse_u64(value: &Value, name: &str) -> Result<u64> {
+    let parsed = match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    parsed.ok_or_else(|| format!("{}: expected an unsigned integer", name))
+}
+
+fn parse_i64(value: &Value, name: &str) -> Result<i64> {
+    let parsed = match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    parsed.ok_or_else(|| format!("{}: expected an integer", name))
+}
+
+/// Doubles are JSON numbers, or the strings `NaN`, `Infinity` and `-Infinity`.
+fn parse_f64(value: &Value, name: &str) -> Result<f64> {
+    let parsed = match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => match s.as_str() {
+            "NaN" => Some(f64::NAN),
+            "Infinity" => Some(f64::INFINITY),
+            "-Infinity" => Some(f64::NEG_INFINITY),
+            other => other.parse().ok(),
+        },
+        _ => None,
+    };
+    parsed.ok_or_else(|| format!("{}: expected a number", name))
+}
@@ -0,0 +1,102 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Requests let through and turned away by `--rate-limit` since startup.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimitCounts {
+    pub accepted: u64,
+    pub rejected: u64,
+}
+
+/// Fixed one-second window limiter for `--rate-limit`. Requests over the limit
+/// are rejected immediately rather than queued, so a flooding exporter sees
+/// `RESOURCE_EXHAUSTED` (or `429` over HTTP) instead of growing latency.
+pub struct RateLimiter {
+    per_second: u64,
+    /// Start of the current window and the requests admitted in it.
+    window: Mutex<(Instant, u64)>,
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: u64) -> Self {
+        Self {
+            per_second,
+            window: Mutex::new((Instant::now(), 0)),
+            accepted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    pub fn per_second(&self) -> u64 {
+        self.per_second
+    }
+
+    /// Counts one request against the current window; `false` means reject it.
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if now.duration_since(window.0) >= WINDOW {
+            *window = (now, 0);
+        }
+        let allowed = window.1 < self.per_second;
+        if allowed {
+            window.1 += 1;
+            self.accepted.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        allowed
+    }
+
+    pub fn counts(&self) -> RateLimitCounts {
+        RateLimitCounts {
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admits_up_to_the_limit_per_window() {
+        let limiter = RateLimiter::new(2);
+        let start = limiter.window.lock().unwrap().0;
+        assert!(limiter.try_acquire_at(start));
+        assert!(limiter.try_acquire_at(start + Duration::from_millis(10)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(20)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(999)));
+        let counts = limiter.counts();
+        assert_eq!((counts.accepted, counts.rejected), (2, 2));
+    }
+
+    #[test]
+    fn next_window_starts_from_zero() {
+        let limiter = RateLimiter::new(1);
+        let start = limiter.window.lock().unwrap().0;
+        assert!(limiter.try_acquire_at(start));
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(500)));
+        // The window rolls over at one second and restarts at that request.
+        let next = start + WINDOW;
+        assert!(limiter.try_acquire_at(next));
+        assert!(!limiter.try_acquire_at(next + Duration::from_millis(999)));
+        assert!(limiter.try_acquire_at(next + WINDOW));
+    }
+
+    #[test]
+    fn zero_limit_rejects_everything() {
+        let limiter = RateLimiter::new(0);
+        assert!(!limiter.try_acquire());
+        assert_eq!(limiter.counts().rejected, 1);
+    }
+}
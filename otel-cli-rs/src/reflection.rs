@@ -0,0 +1,27 @@
+use crate::error::DashboardError;
+use tonic_reflection::server::{Builder, ServerReflection, ServerReflectionServer};
+
+/// `FileDescriptorSet` of the OTLP metrics protos, compiled by `build.rs`.
+const OTLP_DESCRIPTOR_SET: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/otlp_descriptor_set.bin"));
+
+/// The gRPC reflection service for `--reflection`, describing `MetricsService`,
+/// the health service unless `--no-health` is given, and reflection itself, so
+/// `grpcurl` and `grpcui` work without the proto files.
+pub fn service(health: bool) -> Result<ServerReflectionServer<impl ServerReflection>, DashboardError> {
+    let mut builder = Builder::configure().register_encoded_file_descriptor_set(OTLP_DESCRIPTOR_SET);
+    if health {
+        builder = builder.register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET);
+    }
+    builder
+        .build()
+        .map_err(|e| DashboardError::Config(format!("cannot build the reflection service: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn builds_from_the_compiled_descriptors() {
+        assert!(super::service(true).is_ok());
+        assert!(super::service(false).is_ok());
+    }
+}
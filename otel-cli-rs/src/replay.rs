@@ -0,0 +1,179 @@
+use crate::error::DashboardError;
+use crate::metrics::{MetricInfo, MetricKind, MetricPoint, UiMessage};
+use chrono::DateTime;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Unix seconds from a number of seconds, milliseconds, microseconds or
+/// nanoseconds (told apart by magnitude), or from an RFC 3339 date.
+fn parse_timestamp(text: &str) -> Option<u64> {
+    if let Ok(mut secs) = text.parse::<f64>() {
+        if !secs.is_finite() || secs < 0.0 {
+            return None;
+        }
+        // 1e11 seconds is past the year 5000, so anything larger is a finer unit.
+        while secs >= 1e11 {
+            secs /= 1000.0;
+        }
+        return Some(secs as u64);
+    }
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .and_then(|date| u64::try_from(date.timestamp()).ok())
+}
+
+/// The metric of a `timestamp,value` file written by `e`, whose name is
+/// `<metric>-<epoch>.csv`.
+fn metric_from_file_name(path: &Path) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    match stem.rsplit_once('-') {
+        Some((metric, epoch)) if !metric.is_empty() && epoch.bytes().all(|b| b.is_ascii_digit()) => {
+            metric.to_string()
+        }
+        _ => stem,
+    }
+}
+
+/// One `timestamp,metric,value` or `timestamp,value` row. The metric sits
+/// between the first and last comma, so it may contain commas itself.
+fn parse_row(line: &str, default_metric: &str) -> Result<(String, MetricPoint), String> {
+    let (timestamp, rest) = line.split_once(',').ok_or("expected timestamp,metric,value")?;
+    let (metric, value) = match rest.rsplit_once(',') {
+        Some((metric, value)) => (metric.trim().trim_matches('"'), value),
+        None => (default_metric, rest),
+    };
+    if metric.is_empty() {
+        return Err("missing metric name".to_string());
+    }
+    let timestamp = parse_timestamp(timestamp.trim().trim_matches('"'))
+        .ok_or_else(|| format!("invalid timestamp '{}'", timestamp.trim()))?;
+    let value: f64 = value
+        .trim()
+        .trim_matches('"')
+        .parse()
+        .map_err(|e| format!("invalid value '{}': {}", value.trim(), e))?;
+    Ok((metric.to_string(), MetricPoint { timestamp, value }))
+}
+
+/// The points of a `--replay-csv` file, in time order.
+pub fn load_csv(path: &Path) -> Result<Vec<(String, MetricPoint)>, DashboardError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| DashboardError::Config(format!("cannot read {}: {}", path.display(), e)))?;
+    Ok(parse_csv(&text, path))
+}
+
+/// The rows of `text`, read from `path`, sorted by time. A first line that
+/// doesn't parse is taken as the header and skipped quietly; later ones are
+/// skipped with a warning.
+fn parse_csv(text: &str, path: &Path) -> Vec<(String, MetricPoint)> {
+    let default_metric = metric_from_file_name(path);
+
+    let mut points = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parse_row(line, &default_metric) {
+            Ok(point) => points.push(point),
+            Err(_) if index == 0 => {}
+            Err(e) => tracing::warn!("Skipping line {} of {}: {}", index + 1, path.display(), e),
+        }
+    }
+    // The graph expects each series in time order; spreadsheets don't promise it.
+    points.sort_by_key(|(_, point)| point.timestamp);
+    points
+}
+
+/// Feeds loaded points to the TUI as gauges, without going through the
+/// receiver, and returns how many were sent.
+pub fn replay(
+    path: &Path,
+    points: Vec<(String, MetricPoint)>,
+    tx: &UnboundedSender<UiMessage>,
+) -> Result<usize, DashboardError> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (name, _) in &points {
+        *counts.entry(name).or_default() += 1;
+    }
+    let send = |message| tx.send(message).map_err(|e| DashboardError::Channel(e.to_string()));
+    for (name, count) in &counts {
+        send(UiMessage::NewMetric(name.to_string()))?;
+        send(UiMessage::MetricInfo {
+            name: name.to_string(),
+            info: MetricInfo {
+                kind: Some(MetricKind::Gauge),
+                ..Default::default()
+            },
+        })?;
+        send(UiMessage::MetricUpdate {
+            name: name.to_string(),
+            update: format!("{}: {} point(s) from {}", name, count, path.display()),
+            source: None,
+            listener: None,
+        })?;
+    }
+    let sent = points.len();
+    send(UiMessage::MetricDataPointBatch(points))?;
+
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_unit_is_told_by_magnitude() {
+        let cases = [
+            ("1700000000", Some(1_700_000_000)),
+            ("1700000000.9", Some(1_700_000_000)),
+            ("1700000000123", Some(1_700_000_000)),
+            ("1700000000123456", Some(1_700_000_000)),
+            ("1700000000123456789", Some(1_700_000_000)),
+            // Just below the cut-off is still seconds.
+            ("99999999999", Some(99_999_999_999)),
+            ("2023-11-14T22:13:20Z", Some(1_700_000_000)),
+            ("2023-11-14T23:13:20+01:00", Some(1_700_000_000)),
+            ("-1", None),
+            ("NaN", None),
+            ("inf", None),
+            ("yesterday", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_timestamp(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn rows_may_quote_fields_and_name_metrics_with_commas() {
+        let point = |timestamp, value| MetricPoint { timestamp, value };
+        assert_eq!(parse_row("1700000000,cpu,0.5", "file"), Ok(("cpu".to_string(), point(1_700_000_000, 0.5))));
+        assert_eq!(
+            parse_row(r#""1700000000","cpu","0.5""#, "file"),
+            Ok(("cpu".to_string(), point(1_700_000_000, 0.5)))
+        );
+        assert_eq!(
+            parse_row(r#"1700000000,"http{method=GET,code=200}",3"#, "file"),
+            Ok(("http{method=GET,code=200}".to_string(), point(1_700_000_000, 3.0)))
+        );
+        assert_eq!(parse_row("1700000000,a,b,4", "file"), Ok(("a,b".to_string(), point(1_700_000_000, 4.0))));
+        assert_eq!(parse_row("1700000000, 7", "file"), Ok(("file".to_string(), point(1_700_000_000, 7.0))));
+        assert!(parse_row("1700000000", "file").is_err());
+        assert!(parse_row("1700000000,\"\",1", "file").is_err());
+        assert!(parse_row("1700000000,cpu,high", "file").is_err());
+    }
+
+    #[test]
+    fn only_a_first_line_that_fails_is_taken_as_the_header() {
+        let text = "timestamp,metric,value\n\n1700000002,cpu,2\nnot,a,row\n1700000001,cpu,1\n";
+        let points = parse_csv(text, Path::new("export.csv"));
+        let values: Vec<_> = points.iter().map(|(name, point)| (name.as_str(), point.timestamp, point.value)).collect();
+        assert_eq!(values, vec![("cpu", 1_700_000_001, 1.0), ("cpu", 1_700_000_002, 2.0)]);
+
+        // Without a header the first row is data.
+        let points = parse_csv("1700000000,5\n", Path::new("latency-1700000000.csv"));
+        assert_eq!(points, vec![("latency".to_string(), MetricPoint { timestamp: 1_700_000_000, value: 5.0 })]);
+    }
+}
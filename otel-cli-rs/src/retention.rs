@@ -0,0 +1,33 @@
+use crate::rule::{parse_name_value, try_from_str};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// A per-metric history size from `--history-for "http.latency=1000"`,
+/// overriding `--history` for that metric alone.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HistoryOverride {
+    pub metric: String,
+    pub points: usize,
+}
+
+impl FromStr for HistoryOverride {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (metric, points) = parse_name_value(rule, "http.latency=1000")?;
+        let points: usize = points
+            .parse()
+            .map_err(|e| format!("invalid number of points in '{}': {}", rule, e))?;
+        if points == 0 {
+            return Err(format!("'{}' must keep at least one point", rule));
+        }
+
+        Ok(Self {
+            metric: metric.to_string(),
+            points,
+        })
+    }
+}
+
+try_from_str!(HistoryOverride);
@@ -0,0 +1,31 @@
+/// Splits a `NAME=VALUE` rule such as `--threshold "http.latency=250"` into
+/// its trimmed name and value. The split is on the last `=`, so a name that
+/// contains one still parses; `example` shows the expected form when there is
+/// no `=` at all.
+pub fn parse_name_value<'a>(rule: &'a str, example: &str) -> Result<(&'a str, &'a str), String> {
+    let (name, value) = rule
+        .rsplit_once('=')
+        .ok_or_else(|| format!("'{}' has no '='; expected e.g. {}", rule, example))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("'{}' is missing a metric name", rule));
+    }
+    Ok((name, value.trim()))
+}
+
+/// Implements `TryFrom<String>` through `FromStr`, so a rule type marked
+/// `#[serde(try_from = "String")]` reads from the config file exactly as it
+/// parses on the command line.
+macro_rules! try_from_str {
+    ($rule:ty) => {
+        impl TryFrom<String> for $rule {
+            type Error = String;
+
+            fn try_from(rule: String) -> Result<Self, Self::Error> {
+                rule.parse()
+            }
+        }
+    };
+}
+
+pub(crate) use try_from_str;
@@ -0,0 +1,129 @@
+use crate::metrics::{MetricsReceiver, ReceiverCounters};
+use opentelemetry_proto::tonic::collector::metrics::v1::{
+    metrics_service_client::MetricsServiceClient, ExportMetricsServiceRequest,
+};
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue};
+use opentelemetry_proto::tonic::metrics::v1::{
+    metric::Data, number_data_point::Value, AggregationTemporality, Gauge, Metric,
+    NumberDataPoint, ResourceMetrics, ScopeMetrics, Sum,
+};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tonic::transport::Channel;
+
+fn now_unix_nano() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+fn counter(name: &str, description: &str, value: u64, start: u64, now: u64) -> Metric {
+    Metric {
+        name: name.to_string(),
+        description: description.to_string(),
+        data: Some(Data::Sum(Sum {
+            data_points: vec![NumberDataPoint {
+                start_time_unix_nano: start,
+                time_unix_nano: now,
+                value: Some(Value::AsInt(value as i64)),
+                ..Default::default()
+            }],
+            aggregation_temporality: AggregationTemporality::Cumulative as i32,
+            is_monotonic: true,
+        })),
+        ..Default::default()
+    }
+}
+
+fn gauge(name: &str, description: &str, value: u64, now: u64) -> Metric {
+    Metric {
+        name: name.to_string(),
+        description: description.to_string(),
+        data: Some(Data::Gauge(Gauge {
+            data_points: vec![NumberDataPoint {
+                time_unix_nano: now,
+                value: Some(Value::AsInt(value as i64)),
+                ..Default::default()
+            }],
+        })),
+        ..Default::default()
+    }
+}
+
+fn build_request(counters: ReceiverCounters, start: u64) -> ExportMetricsServiceRequest {
+    let now = now_unix_nano();
+    let service_name = KeyValue {
+        key: "service.name".to_string(),
+        value: Some(AnyValue {
+            value: Some(any_value::Value::StringValue(env!("CARGO_PKG_NAME").to_string())),
+        }),
+    };
+
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            resource: Some(Resource {
+                attributes: vec![service_name],
+                ..Default::default()
+            }),
+            scope_metrics: vec![ScopeMetrics {
+                scope: Some(InstrumentationScope {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    ..Default::default()
+                }),
+                metrics: vec![
+                    counter("otel_dashboard.requests", "Export requests received", counters.requests, start, now),
+                    counter(
+                        "otel_dashboard.messages_dropped",
+                        "Messages that could not be delivered to the UI",
+                        counters.dropped_messages,
+                        start,
+                        now,
+                    ),
+                    gauge(
+                        "otel_dashboard.metrics_discovered",
+                        "Distinct metric names seen",
+                        counters.discovered_metrics,
+                        now,
+                    ),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    }
+}
+
+/// Exports the receiver's own counters to `endpoint` every `interval`. A failed
+/// connection or export is logged and retried on the next tick. The counters are
+/// already aggregated by the receiver, so they go out as a hand-built request on
+/// the `opentelemetry-proto` client rather than through the OpenTelemetry SDK.
+pub async fn run(endpoint: String, interval: Duration, receiver: Arc<MetricsReceiver>) {
+    let start = now_unix_nano();
+    let mut client: Option<MetricsServiceClient<Channel>> = None;
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+
+        if client.is_none() {
+            match MetricsServiceClient::connect(endpoint.clone()).await {
+                Ok(connected) => client = Some(connected),
+                Err(e) => {
+                    tracing::warn!("Self-metrics endpoint {} unreachable: {}", endpoint, e);
+                    continue;
+                }
+            }
+        }
+
+        let request = build_request(receiver.counters(), start);
+        if let Some(connected) = client.as_mut() {
+            if let Err(status) = connected.export(request).await {
+                tracing::warn!("Self-metrics export to {} failed: {}", endpoint, status);
+                client = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::error::DashboardError;
+use crate::metrics::UiMessage;
+use opentelemetry_proto::tonic::collector::metrics::v1::{
+    metrics_service_client::MetricsServiceClient, ExportMetricsServiceRequest,
+};
+use opentelemetry_proto::tonic::metrics::v1::{
+    metric::Data, number_data_point::Value, Gauge, Metric, NumberDataPoint, ResourceMetrics,
+    ScopeMetrics,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{sleep, timeout};
+
+const SELFTEST_METRIC: &str = "otel_cli.selftest";
+const CONNECT_ATTEMPTS: u32 = 20;
+const ROUND_TRIP_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn synthetic_request() -> ExportMetricsServiceRequest {
+    let point = NumberDataPoint {
+        value: Some(Value::AsInt(1)),
+        ..Default::default()
+    };
+    let metric = Metric {
+        name: SELFTEST_METRIC.to_string(),
+        data: Some(Data::Gauge(Gauge {
+            data_points: vec![point],
+        })),
+        ..Default::default()
+    };
+
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            scope_metrics: vec![ScopeMetrics {
+                metrics: vec![metric],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    }
+}
+
+/// A wildcard listen address (`0.0.0.0` or `[::]`) isn't something to dial, so
+/// the self-test connects to the loopback address of the same family instead.
+fn dial_address(addr: SocketAddr) -> SocketAddr {
+    if !addr.ip().is_unspecified() {
+        return addr;
+    }
+    let loopback = match addr.ip() {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+    };
+    SocketAddr::new(loopback, addr.port())
+}
+
+async fn connect(addr: SocketAddr) -> Result<MetricsServiceClient<tonic::transport::Channel>, DashboardError> {
+    let endpoint = format!("http://{}", dial_address(addr));
+    let mut last_error = None;
+
+    // The server is spawned concurrently, so give it a moment to bind.
+    for _ in 0..CONNECT_ATTEMPTS {
+        match MetricsServiceClient::connect(endpoint.clone()).await {
+            Ok(client) => return Ok(client),
+            Err(e) => last_error = Some(e),
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    Err(DashboardError::SelfTest(format!(
+        "could not connect to {}: {}",
+        endpoint,
+        last_error.map(|e| e.to_string()).unwrap_or_default()
+    )))
+}
+
+/// Sends a synthetic export to the local receiver and waits for it to surface
+/// as a `NewMetric` on the UI channel.
+pub async fn run(addr: SocketAddr, mut rx: UnboundedReceiver<UiMessage>) -> Result<(), DashboardError> {
+    let mut client = connect(addr).await?;
+
+    client
+        .export(synthetic_request())
+        .await
+        .map_err(|status| DashboardError::SelfTest(format!("export rejected: {}", status)))?;
+
+    let round_trip = async {
+        while let Some(message) = rx.recv().await {
+            if let UiMessage::NewMetric(name) = message {
+                if name == SELFTEST_METRIC {
+                    return Ok(());
+                }
+            }
+        }
+        Err(DashboardError::Channel("UI channel closed before the metric arrived".to_string()))
+    };
+
+    timeout(ROUND_TRIP_TIMEOUT, round_trip)
+        .await
+        .map_err(|_| DashboardError::SelfTest("timed out waiting for the metric".to_string()))?
+}
@@ -0,0 +1,79 @@
+use crate::error::DashboardError;
+use crate::ui::{SortMode, ThemeChoice};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const APP_DIR: &str = "otel-dashboard";
+const SESSION_FILE: &str = "session.toml";
+
+/// TUI view settings saved on exit and reloaded by `--restore-session`.
+/// Every field is optional so older or hand-edited files still load.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub selected_metric: Option<String>,
+    pub history: Option<usize>,
+    /// `line`, `scatter` or `bar`.
+    pub chart_style: Option<String>,
+    /// Graph time window in seconds; absent means everything retained.
+    pub time_window_secs: Option<u64>,
+    pub show_quantiles: Option<bool>,
+    pub log_scale: Option<bool>,
+    pub normalize: Option<bool>,
+    pub compact: Option<bool>,
+    /// Percent of the height given to the metric list.
+    pub split: Option<u16>,
+    /// `name` or `arrival`.
+    pub sort_mode: Option<SortMode>,
+    /// `auto`, `dark` or `light`.
+    pub theme: Option<ThemeChoice>,
+}
+
+/// `otel-dashboard/session.toml` under the platform config directory:
+/// `$XDG_CONFIG_HOME` or `~/.config` on Linux, `~/Library/Application Support`
+/// on macOS and `%APPDATA%` on Windows.
+pub fn path() -> Option<PathBuf> {
+    let env_dir = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let config_dir = if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    };
+    config_dir.map(|dir| dir.join(APP_DIR).join(SESSION_FILE))
+}
+
+/// The saved session, or the defaults when there is none yet.
+pub fn load() -> Result<Session, DashboardError> {
+    let Some(path) = path() else {
+        return Ok(Session::default());
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Session::default()),
+        Err(e) => {
+            return Err(DashboardError::Config(format!("cannot read {}: {}", path.display(), e)))
+        }
+    };
+    toml::from_str(&contents).map_err(|e| {
+        DashboardError::Config(format!(
+            "cannot parse session {}: {} (delete it to start fresh)",
+            path.display(),
+            e.to_string().trim_end()
+        ))
+    })
+}
+
+pub fn save(session: &Session) -> Result<(), DashboardError> {
+    let path = path().ok_or_else(|| {
+        DashboardError::Config("no config directory to save the session in".to_string())
+    })?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let contents = toml::to_string(session)
+        .map_err(|e| DashboardError::Config(format!("cannot serialize session: {}", e)))?;
+    std::fs::write(&path, contents)?;
+    Ok(())
+}
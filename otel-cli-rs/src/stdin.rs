@@ -0,0 +1,65 @@
+use crate::error::DashboardError;
+use crate::metrics::MetricsReceiver;
+use opentelemetry_proto::tonic::collector::metrics::v1::{
+    metrics_service_server::MetricsService, ExportMetricsServiceRequest,
+};
+use prost::Message;
+use std::io;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+/// Largest frame accepted when `--max-recv-size` isn't given; the gRPC default.
+const DEFAULT_MAX_FRAME: usize = 4 * 1024 * 1024;
+
+/// Feeds length-delimited export requests from stdin (the `--capture` framing)
+/// through `export` until stdin closes, returning how many were read.
+pub async fn read(receiver: Arc<MetricsReceiver>, max_frame: Option<usize>) -> Result<u64, DashboardError> {
+    let max_frame = max_frame.unwrap_or(DEFAULT_MAX_FRAME);
+    let mut reader = BufReader::new(tokio::io::stdin());
+    let mut frame = Vec::new();
+    let mut requests = 0;
+
+    while let Some(length) = read_length(&mut reader).await? {
+        if length > max_frame {
+            // A bogus length usually means the input isn't framed at all, so
+            // there is nothing sensible to resynchronize on.
+            return Err(DashboardError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame of {} bytes exceeds the {} byte limit", length, max_frame),
+            )));
+        }
+        frame.resize(length, 0);
+        reader.read_exact(&mut frame).await?;
+        requests += 1;
+
+        let request = match ExportMetricsServiceRequest::decode(frame.as_slice()) {
+            Ok(request) => request,
+            Err(e) => {
+                tracing::warn!("Skipping undecodable frame {} from stdin: {}", requests, e);
+                continue;
+            }
+        };
+        if let Err(status) = receiver.export(tonic::Request::new(request)).await {
+            tracing::warn!("Frame {} from stdin was rejected: {}", requests, status.message());
+        }
+    }
+
+    Ok(requests)
+}
+
+/// Reads a protobuf varint length prefix, or `None` on a clean end of input.
+async fn read_length<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<usize>> {
+    let mut length = 0u64;
+    for i in 0..10 {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        length |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(Some(length as usize));
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "frame length prefix is not a valid varint"))
+}
@@ -0,0 +1,104 @@
+use crate::error::DashboardError;
+use crate::metrics::{self, MetricsReceiver, ReceiverOptions, UiMessage};
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::common::v1::InstrumentationScope;
+use opentelemetry_proto::tonic::metrics::v1::{
+    metric::Data, number_data_point::Value, AggregationTemporality, Gauge, Histogram,
+    HistogramDataPoint, Metric, NumberDataPoint, ResourceMetrics, ScopeMetrics, Sum,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// A receiver that is only reached by calling `export` on it, with the UI
+/// channel handed back for inspection. Nothing is bound, so any number can
+/// exist side by side.
+pub fn in_memory_receiver(
+    options: ReceiverOptions,
+) -> Result<(Arc<MetricsReceiver>, UnboundedReceiver<UiMessage>), DashboardError> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    // Dropping the sender right away just ends the control listener.
+    let (_, control_rx) = mpsc::unbounded_channel();
+    Ok((metrics::create_receiver(options, tx, control_rx)?, rx))
+}
+
+/// One resource and one scope holding `metrics`.
+pub fn request(metrics: Vec<Metric>) -> ExportMetricsServiceRequest {
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            scope_metrics: vec![ScopeMetrics {
+                metrics,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    }
+}
+
+/// One resource with a scope per `(scope name, metrics)` pair, in order.
+pub fn scoped_request(scopes: Vec<(&str, Vec<Metric>)>) -> ExportMetricsServiceRequest {
+    let scope_metrics = scopes
+        .into_iter()
+        .map(|(name, metrics)| ScopeMetrics {
+            scope: Some(InstrumentationScope {
+                name: name.to_string(),
+                ..Default::default()
+            }),
+            metrics,
+            ..Default::default()
+        })
+        .collect();
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            scope_metrics,
+            ..Default::default()
+        }],
+    }
+}
+
+fn number_point(value: f64) -> NumberDataPoint {
+    NumberDataPoint {
+        value: Some(Value::AsDouble(value)),
+        ..Default::default()
+    }
+}
+
+pub fn gauge(name: &str, value: f64) -> Metric {
+    Metric {
+        name: name.to_string(),
+        data: Some(Data::Gauge(Gauge {
+            data_points: vec![number_point(value)],
+        })),
+        ..Default::default()
+    }
+}
+
+/// A cumulative sum with a single point.
+pub fn sum(name: &str, value: f64, is_monotonic: bool) -> Metric {
+    Metric {
+        name: name.to_string(),
+        data: Some(Data::Sum(Sum {
+            data_points: vec![number_point(value)],
+            aggregation_temporality: AggregationTemporality::Cumulative as i32,
+            is_monotonic,
+        })),
+        ..Default::default()
+    }
+}
+
+/// A delta histogram with one point; `counts` needs one entry more than `bounds`.
+pub fn histogram(name: &str, bounds: &[f64], counts: &[u64]) -> Metric {
+    let point = HistogramDataPoint {
+        count: counts.iter().sum(),
+        explicit_bounds: bounds.to_vec(),
+        bucket_counts: counts.to_vec(),
+        ..Default::default()
+    };
+    Metric {
+        name: name.to_string(),
+        data: Some(Data::Histogram(Histogram {
+            data_points: vec![point],
+            aggregation_temporality: AggregationTemporality::Delta as i32,
+        })),
+        ..Default::default()
+    }
+}
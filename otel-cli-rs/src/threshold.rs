@@ -0,0 +1,64 @@
+use crate::rule::{parse_name_value, try_from_str};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// A reference line from `--threshold "http.latency=250"`, drawn across the
+/// metric's graph with the points above it highlighted.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Threshold {
+    pub metric: String,
+    pub value: f64,
+}
+
+impl FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (metric, value) = parse_name_value(rule, "http.latency=250")?;
+        let value: f64 = value
+            .parse()
+            .map_err(|e| format!("invalid threshold in '{}': {}", rule, e))?;
+        if !value.is_finite() {
+            return Err(format!("threshold in '{}' must be a finite number", rule));
+        }
+
+        Ok(Self {
+            metric: metric.to_string(),
+            value,
+        })
+    }
+}
+
+try_from_str!(Threshold);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_name_and_value() {
+        let threshold: Threshold = " http.latency = 250.5 ".parse().unwrap();
+        assert_eq!(threshold, Threshold { metric: "http.latency".to_string(), value: 250.5 });
+    }
+
+    #[test]
+    fn name_may_contain_equals() {
+        let threshold: Threshold = "ratio{a=b}=0.5".parse().unwrap();
+        assert_eq!(threshold.metric, "ratio{a=b}");
+        assert_eq!(threshold.value, 0.5);
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        let rules = ["http.latency", "=250", "  =250", "http.latency=", "http.latency=fast", "http.latency=NaN", "http.latency=inf"];
+        for rule in rules {
+            assert!(rule.parse::<Threshold>().is_err(), "{:?} parsed", rule);
+        }
+        assert_eq!(
+            "http.latency".parse::<Threshold>().unwrap_err(),
+            "'http.latency' has no '='; expected e.g. http.latency=250"
+        );
+        assert_eq!("=250".parse::<Threshold>().unwrap_err(), "'=250' is missing a metric name");
+    }
+}
@@ -1,264 +1,2757 @@
+use crate::alert::AlertRule;
+use crate::alias::MetricAlias;
+use crate::retention::HistoryOverride;
+use crate::threshold::Threshold;
+use crate::clipboard::Clipboard;
+use crate::command::{Command, ExportFormat};
 use crate::error::DashboardError;
-use crate::metrics::{MetricPoint, UiMessage};
+use crate::export;
+use crate::fuzzy;
+use crate::session::{self, Session};
+use crate::metrics::{
+    format_decimal, format_value, quantile_series_name, BucketColumn, ControlMessage, MetricInfo,
+    MetricKind, MetricPoint, ReceiverStats, SeriesStats, Temporality, UiMessage, DEFAULT_PRECISION,
+};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     prelude::*,
-    widgets::{Axis, Block, Borders, Chart, Dataset, List, ListItem, ListState},
+    widgets::{
+        Axis, Block, Borders, Chart, Clear, Dataset, LegendPosition, List, ListItem, ListState,
+        Paragraph, Wrap,
+    },
     Terminal,
 };
-use std::collections::{HashMap, VecDeque};
-use std::io;
-use tokio::sync::mpsc::UnboundedReceiver;
-use chrono::{NaiveDateTime, Timelike};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::io::{self, IsTerminal, Write};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use tokio_stream::StreamExt;
+use chrono::DateTime;
+
+/// Set while `run_tui` owns the terminal, so the panic hook knows to restore it.
+static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+const MAX_POINTS: usize = 100;
+const MAX_UPDATES: usize = 100;
+const MAX_SERIES: usize = 1000;
+const SPARKLINE_POINTS: usize = 10;
+const SPARKLINE_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_millis(16);
+const MAX_REFRESH_INTERVAL: Duration = Duration::from_millis(1000);
+/// Share of the screen height given to the metric list, in percent.
+const DEFAULT_SPLIT: u16 = 30;
+const MIN_SPLIT: u16 = 10;
+const MAX_SPLIT: u16 = 90;
+const SPLIT_STEP: u16 = 5;
+/// Shortest gap between `--realtime` frames, so a burst of messages is drawn
+/// once rather than once per message.
+const REALTIME_FRAME: Duration = Duration::from_millis(16);
+const STATUS_MESSAGE_TTL: Duration = Duration::from_secs(3);
+const DEFAULT_UPDATE_WINDOW: Duration = Duration::from_secs(10);
+/// Table interval of the `--watch` output used when stdout isn't a terminal.
+const FALLBACK_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+const QUANTILE_COLORS: [Color; 3] = [Color::Green, Color::Yellow, Color::Red];
+const MAX_LEGEND_ENTRIES: usize = 6;
+/// Heatmap cell colors from the smallest to the largest bucket count.
+const HEATMAP_COLORS: [Color; 8] = [
+    Color::Indexed(17),
+    Color::Indexed(19),
+    Color::Indexed(26),
+    Color::Indexed(32),
+    Color::Indexed(41),
+    Color::Indexed(148),
+    Color::Indexed(214),
+    Color::Indexed(196),
+];
+/// Graph time windows selectable with `[`/`]`, narrowest first; `None` shows everything retained.
+const TIME_WINDOWS: [Option<Duration>; 4] = [
+    Some(Duration::from_secs(60)),
+    Some(Duration::from_secs(5 * 60)),
+    Some(Duration::from_secs(15 * 60)),
+    None,
+];
+/// A step this many times the median step between points is drawn as a gap.
+const GAP_FACTOR: f64 = 3.0;
+/// Deepest x-axis zoom, as a power of two: 1/64 of the time window.
+const MAX_ZOOM: u32 = 6;
+/// Metrics listed in the cardinality popup.
+const MAX_CARDINALITY_ROWS: usize = 20;
+
+/// The `/` metric finder: the query typed so far and the highlighted match.
+#[derive(Debug, Default)]
+struct Finder {
+    query: String,
+    highlighted: usize,
+}
+
+/// Points kept for `name`: its `--history-for`, or the one of the metric a
+/// quantile series (`latency[p99]`) derives from, or `history`.
+fn history_limit(history_for: &HashMap<String, usize>, history: usize, name: &str) -> usize {
+    let base = name.strip_suffix(']').and_then(|n| n.rsplit_once('[')).map(|(base, _)| base);
+    history_for
+        .get(name)
+        .or_else(|| history_for.get(base?))
+        .copied()
+        .unwrap_or(history)
+}
+
+/// Axis bounds that always span a non-zero range: a flat series is padded by
+/// 10% of its value (or 1 around zero) so it renders centered, and reversed
+/// bounds are swapped.
+fn padded_bounds(min: f64, max: f64) -> [f64; 2] {
+    let (min, max) = if min <= max { (min, max) } else { (max, min) };
+    if max - min > f64::EPSILON {
+        return [min, max];
+    }
+    let pad = if min.abs() > f64::EPSILON { min.abs() * 0.1 } else { 1.0 };
+    [min - pad, max + pad]
+}
+
+/// A rectangle of the given percentage size centered inside `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    // Plain arithmetic rather than a percentage layout, so the popup stays
+    // centered and inside `area` at any terminal size.
+    let scale = |length: u16, percent: u16| (u32::from(length) * u32::from(percent.min(100)) / 100) as u16;
+    let width = scale(area.width, percent_x);
+    let height = scale(area.height, percent_y);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// `text` cut to `width` characters, ending in `…` when anything was cut.
+fn ellipsize(text: &str, width: usize) -> Cow<'_, str> {
+    if text.chars().count() <= width {
+        return Cow::Borrowed(text);
+    }
+    let kept: String = text.chars().take(width.saturating_sub(1)).collect();
+    Cow::Owned(kept + "…")
+}
+
+/// How many `hh:mm:ss` labels fit on an x axis `width` cells wide, keeping
+/// a few cells of space between them.
+fn x_label_count(width: u16) -> usize {
+    const LABEL_WIDTH: u16 = 8 + 4;
+    usize::from(width.saturating_sub(10) / LABEL_WIDTH).clamp(2, 7)
+}
+
+/// Time zone used for graph axis labels and report timestamps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Timezone {
+    #[default]
+    Local,
+    Utc,
+}
+
+impl Timezone {
+    /// Formats Unix seconds with `format`; `None` when chrono can't represent them.
+    fn format(self, secs: i64, format: &str) -> Option<String> {
+        let utc = DateTime::from_timestamp(secs, 0)?;
+        Some(match self {
+            Timezone::Local => utc.with_timezone(&chrono::Local).format(format).to_string(),
+            Timezone::Utc => utc.format(format).to_string(),
+        })
+    }
+}
+
+/// Theme picked at startup; `auto` follows the terminal's background.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeChoice {
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+impl ThemeChoice {
+    /// The theme selected with `:theme <name>`.
+    fn named(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "dark" => Some(Self::Dark),
+            "light" => Some(Self::Light),
+            _ => None,
+        }
+    }
+}
+
+/// The pane shown when a metric is selected; `u` switches to the other one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultView {
+    #[default]
+    Graph,
+    Updates,
+}
+
+/// How the points of a series are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChartStyle {
+    Line,
+    Scatter,
+    Bar,
+}
+
+impl ChartStyle {
+    fn next(self) -> Self {
+        match self {
+            ChartStyle::Line => ChartStyle::Scatter,
+            ChartStyle::Scatter => ChartStyle::Bar,
+            ChartStyle::Bar => ChartStyle::Line,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ChartStyle::Line => "line",
+            ChartStyle::Scatter => "scatter",
+            ChartStyle::Bar => "bar",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        [ChartStyle::Line, ChartStyle::Scatter, ChartStyle::Bar]
+            .into_iter()
+            .find(|style| style.label() == label)
+    }
+
+    /// Ratatui has no bar dataset, so bars are drawn as a line that rises from
+    /// `base` to each value and back down before moving on to the next x.
+    fn bar_stems(data: &[(f64, f64)], base: f64) -> Vec<(f64, f64)> {
+        data.iter()
+            .flat_map(|&(x, y)| [(x, base), (x, y), (x, base)])
+            .collect()
+    }
+}
+
+/// Order of the metric list, switched with `S`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    Name,
+    Arrival,
+}
+
+impl SortMode {
+    fn next(self) -> Self {
+        match self {
+            SortMode::Name => SortMode::Arrival,
+            SortMode::Arrival => SortMode::Name,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::Arrival => "arrival order",
+        }
+    }
+}
+
+/// Which metric types the list shows, cycled with `f`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KindFilter {
+    All,
+    Gauges,
+    Counters,
+    Histograms,
+}
+
+impl KindFilter {
+    fn next(self) -> Self {
+        match self {
+            KindFilter::All => KindFilter::Gauges,
+            KindFilter::Gauges => KindFilter::Counters,
+            KindFilter::Counters => KindFilter::Histograms,
+            KindFilter::Histograms => KindFilter::All,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            KindFilter::All => "all",
+            KindFilter::Gauges => "gauges",
+            KindFilter::Counters => "counters",
+            KindFilter::Histograms => "histograms",
+        }
+    }
+
+    /// Summaries count as histograms: both describe a distribution. A metric
+    /// whose type isn't known yet only shows up unfiltered.
+    fn admits(self, kind: Option<MetricKind>) -> bool {
+        match self {
+            KindFilter::All => true,
+            KindFilter::Gauges => kind == Some(MetricKind::Gauge),
+            KindFilter::Counters => kind == Some(MetricKind::Sum),
+            KindFilter::Histograms => matches!(
+                kind,
+                Some(MetricKind::Histogram | MetricKind::ExponentialHistogram | MetricKind::Summary)
+            ),
+        }
+    }
+}
+
+/// Colors used by the TUI.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Colors metric series are drawn in, picked by hashing the metric name.
+    pub series_palette: Vec<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            series_palette: vec![
+                Color::Cyan,
+                Color::Magenta,
+                Color::LightBlue,
+                Color::LightGreen,
+                Color::LightRed,
+                Color::LightYellow,
+                Color::Blue,
+                Color::Green,
+            ],
+        }
+    }
+}
+
+impl Theme {
+    /// Colors that stay readable on a light terminal background.
+    fn light() -> Self {
+        Self {
+            series_palette: vec![
+                Color::Blue,
+                Color::Magenta,
+                Color::Red,
+                Color::Green,
+                Color::Cyan,
+                Color::DarkGray,
+                Color::Black,
+                Color::Yellow,
+            ],
+        }
+    }
+
+    fn chosen(choice: ThemeChoice) -> Self {
+        match choice {
+            ThemeChoice::Auto => Self::detect(),
+            ThemeChoice::Dark => Self::default(),
+            ThemeChoice::Light => Self::light(),
+        }
+    }
+
+    /// Light when `COLORFGBG` (set by rxvt, Konsole, iTerm2 and others as
+    /// "fg;bg", with ANSI color numbers) names a light background; dark otherwise.
+    fn detect() -> Self {
+        let background = std::env::var("COLORFGBG")
+            .ok()
+            .and_then(|value| value.rsplit(';').next().and_then(|bg| bg.parse::<u8>().ok()));
+        match background {
+            // White, and the bright colors except bright black.
+            Some(7) | Some(9..=15) => Self::light(),
+            _ => Self::default(),
+        }
+    }
+
+    /// The same metric always gets the same color, however the set of selected
+    /// and overlaid metrics changes. FNV-1a keeps it stable across runs too.
+    fn series_color(&self, metric_name: &str) -> Color {
+        if self.series_palette.is_empty() {
+            return Color::Reset;
+        }
+        let hash = metric_name
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3));
+        self.series_palette[(hash % self.series_palette.len() as u64) as usize]
+    }
+}
+
+/// One line in the chart: a label for the legend, its color and the plotted points.
+struct GraphSeries {
+    name: String,
+    color: Color,
+    data: Vec<(f64, f64)>,
+}
+
+impl GraphSeries {
+    /// Reduces the points to at most `threshold` with largest-triangle-three-buckets,
+    /// which keeps peaks and dips that a plain stride would skip. Only the rendered
+    /// copy is reduced; `metric_data` keeps every point for export.
+    fn downsample(&mut self, threshold: usize) {
+        let data = &self.data;
+        if threshold < 3 || data.len() <= threshold {
+            return;
+        }
+
+        let bucket_size = (data.len() - 2) as f64 / (threshold - 2) as f64;
+        let mut sampled = Vec::with_capacity(threshold);
+        sampled.push(data[0]);
+        let mut previous = data[0];
+        for bucket in 0..threshold - 2 {
+            let start = (bucket as f64 * bucket_size) as usize + 1;
+            let end = ((bucket + 1) as f64 * bucket_size) as usize + 1;
+            let next_end = (((bucket + 2) as f64 * bucket_size) as usize + 1).min(data.len());
+
+            let next = &data[end..next_end];
+            let avg_x = next.iter().map(|p| p.0).sum::<f64>() / next.len() as f64;
+            let avg_y = next.iter().map(|p| p.1).sum::<f64>() / next.len() as f64;
+
+            let area = |p: &(f64, f64)| {
+                ((previous.0 - avg_x) * (p.1 - previous.1) - (previous.0 - p.0) * (avg_y - previous.1)).abs()
+            };
+            let chosen = data[start..end]
+                .iter()
+                .copied()
+                .max_by(|a, b| area(a).total_cmp(&area(b)))
+                .unwrap_or(data[start]);
+            sampled.push(chosen);
+            previous = chosen;
+        }
+        sampled.push(data[data.len() - 1]);
+        self.data = sampled;
+    }
+
+    /// Rescales values to [0, 1] so series of different magnitudes can be
+    /// compared by shape; the real range is kept in the legend label.
+    fn normalize(&mut self, precision: usize) {
+        let min = self.data.iter().map(|p| p.1).reduce(f64::min).unwrap_or(0.0);
+        let max = self.data.iter().map(|p| p.1).reduce(f64::max).unwrap_or(0.0);
+        let span = max - min;
+        for point in &mut self.data {
+            point.1 = if span > 0.0 { (point.1 - min) / span } else { 0.5 };
+        }
+        self.name = format!("{} [{}..{}]", self.name, format_decimal(min, precision), format_decimal(max, precision));
+    }
+
+    /// Splits the points wherever consecutive timestamps are more than `GAP_FACTOR`
+    /// times the median interval apart, so a pause in exports shows up as a gap
+    /// instead of a straight line across it. With `bridge_repeats`, two equal
+    /// values are never split, since `--dedup` stores a run as its two ends.
+    fn segments(&self, bridge_repeats: bool) -> Vec<&[(f64, f64)]> {
+        let mut steps: Vec<f64> = self
+            .data
+            .windows(2)
+            .map(|pair| pair[1].0 - pair[0].0)
+            .filter(|step| *step > 0.0)
+            .collect();
+        if steps.is_empty() {
+            return vec![&self.data];
+        }
+        steps.sort_by(f64::total_cmp);
+        let threshold = steps[steps.len() / 2] * GAP_FACTOR;
+
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for i in 1..self.data.len() {
+            let repeat = bridge_repeats && self.data[i].1 == self.data[i - 1].1;
+            if self.data[i].0 - self.data[i - 1].0 > threshold && !repeat {
+                segments.push(&self.data[start..i]);
+                start = i;
+            }
+        }
+        segments.push(&self.data[start..]);
+        segments
+    }
+
+    /// Replaces values with their `log10`. Zero and negative values have no
+    /// logarithm, so those points are left out.
+    fn log_scale(&mut self) {
+        self.data.retain(|p| p.1 > 0.0);
+        for point in &mut self.data {
+            point.1 = point.1.log10();
+        }
+    }
+}
+
+/// Display settings taken from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct TuiOptions {
+    pub alerts: Vec<AlertRule>,
+    pub aliases: Vec<MetricAlias>,
+    pub thresholds: Vec<Threshold>,
+    /// Start with overlaid series scaled to [0, 1].
+    pub normalize: bool,
+    /// Prefix recent updates with the sender's IP address.
+    pub show_source: bool,
+    /// Prefix updates with the listener they arrived on, for several `--address`es.
+    pub show_listener: bool,
+    /// Points kept per metric; `MAX_POINTS` when unset.
+    pub history: Option<usize>,
+    /// Metrics keeping a different number of points than `history`.
+    pub history_for: Vec<HistoryOverride>,
+    /// Drop the recent updates whenever the selection changes.
+    pub clear_updates_on_select: bool,
+    /// Cap on the number of series kept; `MAX_SERIES` when unset.
+    pub max_series: Option<usize>,
+    /// Start in single-pane mode.
+    pub compact: bool,
+    /// Percent of the height given to the list; `DEFAULT_SPLIT` when unset.
+    pub split: Option<u16>,
+    /// List metrics in the order they first arrived instead of by name.
+    pub no_sort: bool,
+    /// Drop metrics whose latest point is older than this.
+    pub stale_timeout: Option<Duration>,
+    /// Unix seconds of the newest point of `--replay-csv`, used in place of the
+    /// clock so recorded data isn't all stale or outside the time window.
+    pub replay_end: Option<u64>,
+    /// Window of the update counter in the updates title; 10 seconds when unset.
+    pub update_window: Option<Duration>,
+    /// Warn about metrics with more distinct attribute sets than this.
+    pub cardinality_warn: Option<usize>,
+    /// Messages applied per frame; unlimited when unset.
+    pub max_per_frame: Option<usize>,
+    /// Redraw as soon as a message or key arrives instead of on the refresh tick.
+    pub realtime: bool,
+    /// Collapse repeated values within this distance; every point is kept when unset.
+    pub dedup_epsilon: Option<f64>,
+    /// Fixed decimals for displayed values; see `metrics::format_decimal`.
+    pub precision: Option<usize>,
+    pub timezone: Timezone,
+    /// `ThemeChoice::Auto` when unset.
+    pub theme: Option<ThemeChoice>,
+    pub default_view: DefaultView,
+    /// Where exporters can reach the receiver, e.g. `gRPC http://127.0.0.1:4317`.
+    pub listening: String,
+    /// Set by `--restore-session`: the view to start from, saved again on exit.
+    /// Command-line flags still win over it.
+    pub session: Option<Session>,
+}
+
+pub struct TuiState {
+    discovered_metrics: Vec<String>,
+    sort_mode: SortMode,
+    /// Position of each metric in arrival order, for `SortMode::Arrival`.
+    arrival: HashMap<String, u64>,
+    arrivals: u64,
+    /// Limits the list to one metric type; `list_state` indexes `listed_metrics`.
+    kind_filter: KindFilter,
+    recent_updates: VecDeque<String>,
+    /// Each metric's own updates, kept while other metrics are selected.
+    metric_updates: HashMap<String, VecDeque<String>>,
+    /// Arrival times of updates within `update_window`, oldest first, overall
+    /// and per metric.
+    update_times: VecDeque<Instant>,
+    metric_update_times: HashMap<String, VecDeque<Instant>>,
+    update_window: Duration,
+    cardinality_warn: Option<usize>,
+    show_cardinality: bool,
+    clear_updates_on_select: bool,
+    /// Wrap long updates over several rows instead of cutting them off.
+    wrap_updates: bool,
+    list_state: ListState,
+    selected_metric: Option<String>,
+    metric_data: HashMap<String, VecDeque<MetricPoint>>,
+    metric_info: HashMap<String, MetricInfo>,
+    metric_attributes: HashMap<String, BTreeSet<String>>,
+    /// Recent bucket layouts per histogram, oldest first, for the heatmap.
+    histogram_columns: HashMap<String, VecDeque<BucketColumn>>,
+    show_graph: bool,
+    default_view: DefaultView,
+    /// Draw histograms as a time-vs-bucket heatmap instead of lines.
+    show_heatmap: bool,
+    show_detail: bool,
+    clipboard: Clipboard,
+    show_quantiles: bool,
+    stats: ReceiverStats,
+    status_message: Option<(String, Instant)>,
+    /// Digits typed after `g`; `Some("")` means `g` was just pressed.
+    pending_jump: Option<String>,
+    /// Text typed after `:`, while the command line is open.
+    command_line: Option<String>,
+    /// The metric finder, while open.
+    finder: Option<Finder>,
+    started_at: Instant,
+    listening: String,
+    refresh_interval: Duration,
+    chart_style: ChartStyle,
+    alerts: Vec<AlertRule>,
+    /// Metrics whose latest value breaches one of their alert rules.
+    alerting: HashSet<String>,
+    /// Display names for the list, keyed by the original metric name.
+    aliases: HashMap<String, String>,
+    /// Reference lines drawn across a metric's graph, keyed by metric name.
+    thresholds: HashMap<String, f64>,
+    /// Extra metrics drawn on top of the selected metric's graph.
+    overlay_metrics: BTreeSet<String>,
+    normalize: bool,
+    /// Sums graphed the other way round from their default (rate for
+    /// counters, raw values for up-down counters), flipped with `R`.
+    rate_overrides: HashSet<String>,
+    /// Graph delta sums as the running total of their deltas, toggled with `a`.
+    accumulate: bool,
+    /// Running total of every delta received per delta sum, capped at
+    /// `history` like `metric_data`; graphed while `accumulate` is on.
+    running_totals: HashMap<String, VecDeque<MetricPoint>>,
+    /// Timestamp of each series' very first point, which alone can be rated
+    /// against the sum's start time.
+    first_points: HashMap<String, u64>,
+    /// Y-axis bounds pinned per metric with `y`; the others auto-fit every frame.
+    locked_y_bounds: HashMap<String, [f64; 2]>,
+    timezone: Timezone,
+    /// Index into `TIME_WINDOWS` of the span shown in the graph.
+    time_window: usize,
+    /// The graph shows 1/2^`zoom` of the time window's points on the x-axis.
+    zoom: u32,
+    /// Right edge of the graph after panning (epoch seconds); `None` follows
+    /// the latest point.
+    view_end: Option<f64>,
+    /// Plot `log10` of the values; non-positive points are skipped.
+    log_scale: bool,
+    show_scope: bool,
+    show_source: bool,
+    show_listener: bool,
+    /// gRPC listeners each metric has arrived on.
+    metric_listeners: HashMap<String, BTreeSet<SocketAddr>>,
+    /// Show a single full-screen pane instead of the list/graph split.
+    compact: bool,
+    /// Percent of the height given to the list, moved with `<` and `>`.
+    split: u16,
+    /// Points kept per metric before the oldest are dropped.
+    history: usize,
+    /// Points kept for the metrics given a `--history-for`.
+    history_for: HashMap<String, usize>,
+    dedup_epsilon: Option<f64>,
+    precision: Option<usize>,
+    max_series: usize,
+    /// New metrics refused because `max_series` was reached.
+    rejected_series: u64,
+    stale_timeout: Option<Duration>,
+    replay_end: Option<u64>,
+    /// Metrics dropped as stale; the receiver only announces a name once, so
+    /// these are listed again by their next point.
+    pruned: HashSet<String>,
+    theme: Theme,
+    /// What `theme` was picked from, kept for the session.
+    theme_choice: ThemeChoice,
+    /// Save the view with `session::save` when the TUI exits.
+    restore_session: bool,
+}
+
+impl TuiState {
+    fn new(options: TuiOptions) -> Self {
+        let session = options.session.clone().unwrap_or_default();
+        let time_window = TIME_WINDOWS
+            .iter()
+            .position(|w| w.map(|w| w.as_secs()) == session.time_window_secs)
+            .unwrap_or(TIME_WINDOWS.len() - 1);
+        let theme_choice = options.theme.or(session.theme).unwrap_or_default();
+        Self {
+            discovered_metrics: Vec::new(),
+            sort_mode: if options.no_sort {
+                SortMode::Arrival
+            } else {
+                session.sort_mode.unwrap_or(SortMode::Name)
+            },
+            arrival: HashMap::new(),
+            arrivals: 0,
+            kind_filter: KindFilter::All,
+            recent_updates: VecDeque::with_capacity(MAX_UPDATES),
+            metric_updates: HashMap::new(),
+            update_times: VecDeque::new(),
+            metric_update_times: HashMap::new(),
+            update_window: options.update_window.unwrap_or(DEFAULT_UPDATE_WINDOW),
+            cardinality_warn: options.cardinality_warn,
+            show_cardinality: false,
+            clear_updates_on_select: options.clear_updates_on_select,
+            wrap_updates: false,
+            list_state: ListState::default(),
+            show_graph: session.selected_metric.is_some() && options.default_view == DefaultView::Graph,
+            default_view: options.default_view,
+            selected_metric: session.selected_metric,
+            metric_data: HashMap::new(),
+            metric_info: HashMap::new(),
+            metric_attributes: HashMap::new(),
+            histogram_columns: HashMap::new(),
+            show_heatmap: false,
+            show_detail: false,
+            clipboard: Clipboard::default(),
+            show_quantiles: session.show_quantiles.unwrap_or(true),
+            stats: ReceiverStats::default(),
+            status_message: None,
+            pending_jump: None,
+            command_line: None,
+            finder: None,
+            started_at: Instant::now(),
+            listening: options.listening,
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            chart_style: session
+                .chart_style
+                .as_deref()
+                .and_then(ChartStyle::from_label)
+                .unwrap_or(ChartStyle::Line),
+            alerts: options.alerts,
+            alerting: HashSet::new(),
+            aliases: options.aliases.into_iter().map(|a| (a.metric, a.alias)).collect(),
+            thresholds: options.thresholds.into_iter().map(|t| (t.metric, t.value)).collect(),
+            overlay_metrics: BTreeSet::new(),
+            normalize: options.normalize || session.normalize.unwrap_or(false),
+            rate_overrides: HashSet::new(),
+            accumulate: false,
+            running_totals: HashMap::new(),
+            first_points: HashMap::new(),
+            locked_y_bounds: HashMap::new(),
+            timezone: options.timezone,
+            time_window,
+            zoom: 0,
+            view_end: None,
+            log_scale: session.log_scale.unwrap_or(false),
+            show_scope: false,
+            show_source: options.show_source,
+            show_listener: options.show_listener,
+            metric_listeners: HashMap::new(),
+            compact: options.compact || session.compact.unwrap_or(false),
+            split: options.split.or(session.split).unwrap_or(DEFAULT_SPLIT).clamp(MIN_SPLIT, MAX_SPLIT),
+            history: options.history.or(session.history).unwrap_or(MAX_POINTS).max(1),
+            history_for: options.history_for.into_iter().map(|h| (h.metric, h.points)).collect(),
+            dedup_epsilon: options.dedup_epsilon,
+            precision: options.precision,
+            max_series: options.max_series.unwrap_or(MAX_SERIES),
+            rejected_series: 0,
+            stale_timeout: options.stale_timeout,
+            replay_end: options.replay_end,
+            pruned: HashSet::new(),
+            theme: Theme::chosen(theme_choice),
+            theme_choice,
+            restore_session: options.session.is_some(),
+        }
+    }
+
+    /// The view settings `--restore-session` brings back on the next start.
+    fn session(&self) -> Session {
+        Session {
+            selected_metric: self.selected_metric.clone(),
+            history: Some(self.history),
+            chart_style: Some(self.chart_style.label().to_string()),
+            time_window_secs: TIME_WINDOWS[self.time_window].map(|w| w.as_secs()),
+            show_quantiles: Some(self.show_quantiles),
+            log_scale: Some(self.log_scale),
+            normalize: Some(self.normalize),
+            compact: Some(self.compact),
+            split: Some(self.split),
+            sort_mode: Some(self.sort_mode),
+            theme: Some(self.theme_choice),
+        }
+    }
+
+    fn apply(&mut self, message: UiMessage) {
+        match message {
+            UiMessage::NewMetric(metric) => self.add_metric(metric),
+            UiMessage::MetricInfo { name, info } => {
+                self.metric_info.insert(name, info);
+            }
+            UiMessage::MetricAttributes { name, attributes } => {
+                let sets = self.metric_attributes.entry(name.clone()).or_default();
+                sets.insert(attributes);
+                let count = sets.len();
+                if self.cardinality_warn.is_some_and(|limit| count == limit + 1) {
+                    self.flash(format!("{} passed {} attribute sets (C: cardinality)", name, count - 1));
+                }
+            }
+            UiMessage::MetricUpdate { name, update, source, listener } => {
+                if let Some(listener) = listener {
+                    self.metric_listeners.entry(name.clone()).or_default().insert(listener);
+                }
+                let prefix = self.source_prefix(source, listener);
+                self.add_update(name, update, prefix);
+            }
+            UiMessage::MetricDataPointBatch(points) => {
+                for (name, point) in points {
+                    self.add_metric_point(name, point);
+                }
+            }
+            UiMessage::HistogramBuckets { name, column } => {
+                if !self.metric_data.contains_key(&name) {
+                    return;
+                }
+                let history = self.history_of(&name);
+                let columns = self.histogram_columns.entry(name).or_default();
+                columns.push_back(column);
+                if columns.len() > history {
+                    columns.pop_front();
+                }
+            }
+            UiMessage::MetricReappeared { name, gap } => {
+                let update = format!("{}: REAPPEARED after {}s without data", name, gap.as_secs());
+                self.add_update(name.clone(), update, None);
+                self.flash(format!("{} reappeared after {}s; its exporter may have restarted", name, gap.as_secs()));
+            }
+            UiMessage::Stats(stats) => self.stats = stats,
+        }
+    }
+
+    /// Whether `name` may have a series in `metric_data`; new series are refused
+    /// once `max_series` exist so a cardinality explosion can't exhaust memory.
+    fn admits_series(&self, name: &str) -> bool {
+        self.metric_data.contains_key(name) || self.metric_data.len() < self.max_series
+    }
+
+    fn history_of(&self, name: &str) -> usize {
+        history_limit(&self.history_for, self.history, name)
+    }
+
+    fn add_metric(&mut self, metric: String) {
+        if !self.admits_series(&metric) {
+            self.rejected_series += 1;
+            return;
+        }
+        if !self.discovered_metrics.contains(&metric) {
+            self.discovered_metrics.push(metric.clone());
+            self.arrival.insert(metric.clone(), self.arrivals);
+            self.arrivals += 1;
+            if self.sort_mode == SortMode::Name {
+                self.discovered_metrics.sort();
+            }
+            self.metric_data
+                .insert(metric.clone(), VecDeque::with_capacity(self.history_of(&metric)));
+            // A metric selected in a restored session gets highlighted once it shows up.
+            if self.selected_metric.as_ref() == Some(&metric) {
+                let index = self.listed_metrics().iter().position(|m| **m == metric);
+                self.list_state.select(index);
+            } else if self.list_state.selected().is_none() {
+                self.list_state.select(Some(0));
+            }
+        }
+    }
+
+    /// Re-evaluates the alert rules for `name`, logging a line when one starts
+    /// to breach.
+    fn check_alerts(&mut self, name: &str, value: f64) {
+        let breached = self
+            .alerts
+            .iter()
+            .find(|rule| rule.metric == name && rule.is_breached(value))
+            .map(|rule| rule.to_string());
+
+        match breached {
+            Some(rule) => {
+                if self.alerting.insert(name.to_string()) {
+                    let update = format!("{}: ALERT {} (value {})", name, rule, value);
+                    self.add_update(name.to_string(), update, None);
+                }
+            }
+            None => {
+                self.alerting.remove(name);
+            }
+        }
+    }
+
+    fn add_metric_point(&mut self, name: String, point: MetricPoint) {
+        if !self.admits_series(&name) {
+            return;
+        }
+        if self.pruned.remove(&name) {
+            self.add_metric(name.clone());
+        }
+        self.check_alerts(&name, point.value);
+        self.first_points.entry(name.clone()).or_insert(point.timestamp);
+        let history = self.history_of(&name);
+        if self.is_delta_sum(&name) {
+            // Kept before dedup, which would drop repeated deltas from the sum.
+            let totals = self
+                .running_totals
+                .entry(name.clone())
+                .or_insert_with(|| VecDeque::with_capacity(history));
+            let total = totals.back().map_or(0.0, |last| last.value) + point.value;
+            totals.push_back(MetricPoint { value: total, ..point.clone() });
+            if totals.len() > history {
+                totals.pop_front();
+            }
+        }
+        let dedup_epsilon = self.dedup_epsilon;
+        // Derived series (histogram quantiles) never get a NewMetric of their own.
+        let points = self
+            .metric_data
+            .entry(name)
+            .or_insert_with(|| VecDeque::with_capacity(history));
+        // A run of repeats is stored as its first point and a last point that
+        // moves forward, both with the run's value, so the graph stays flat.
+        let repeated = dedup_epsilon.and_then(|epsilon| {
+            let last = points.back()?;
+            ((last.value - point.value).abs() <= epsilon).then_some(last.value)
+        });
+        match repeated {
+            Some(value) if points.len() >= 2 && points[points.len() - 2].value == value => {
+                if let Some(last) = points.back_mut() {
+                    last.timestamp = point.timestamp;
+                }
+                return;
+            }
+            Some(value) => points.push_back(MetricPoint { value, ..point }),
+            None => points.push_back(point),
+        }
+        if points.len() > history {
+            points.pop_front();
+        }
+    }
+
+    /// Seconds since the latest point of `name`, by the exporter's timestamps.
+    fn age_secs(&self, name: &str) -> Option<u64> {
+        let latest = self.metric_data.get(name)?.back()?.timestamp;
+        Some(self.now_secs().saturating_sub(latest))
+    }
+
+    /// The current time in epoch seconds, or the end of a replayed recording.
+    fn now_secs(&self) -> u64 {
+        self.replay_end.unwrap_or_else(|| chrono::Utc::now().timestamp().max(0) as u64)
+    }
+
+    /// Whether `name` is past two thirds of `--stale-timeout`, so it is dimmed
+    /// before it disappears.
+    fn is_going_stale(&self, name: &str) -> bool {
+        match (self.stale_timeout, self.age_secs(name)) {
+            (Some(timeout), Some(age)) => age * 3 >= timeout.as_secs() * 2,
+            _ => false,
+        }
+    }
+
+    /// Removes every series whose latest point is older than `--stale-timeout`,
+    /// keeping the highlighted row on the same metric where it survives.
+    /// Metadata stays, so a metric that comes back keeps its type and unit.
+    fn prune_stale(&mut self) {
+        let Some(timeout) = self.stale_timeout else {
+            return;
+        };
+        let stale: Vec<String> = self
+            .metric_data
+            .keys()
+            .filter(|name| self.age_secs(name).is_some_and(|age| age >= timeout.as_secs()))
+            .cloned()
+            .collect();
+        if stale.is_empty() {
+            return;
+        }
+
+        let highlighted = self.highlighted_metric().cloned();
+        for name in &stale {
+            // `metric_info` and `metric_attributes` stay: the receiver only
+            // announces them once, and the metric may report again.
+            self.metric_data.remove(name);
+            self.metric_listeners.remove(name);
+            self.histogram_columns.remove(name);
+            self.metric_updates.remove(name);
+            self.metric_update_times.remove(name);
+            self.alerting.remove(name);
+            self.overlay_metrics.remove(name);
+            self.locked_y_bounds.remove(name);
+            self.rate_overrides.remove(name);
+            self.running_totals.remove(name);
+            self.first_points.remove(name);
+            if self.selected_metric.as_ref() == Some(name) {
+                self.selected_metric = None;
+                self.show_graph = false;
+            }
+        }
+        for name in &stale {
+            self.arrival.remove(name);
+            // Derived series such as `latency[p99]` were never listed, so their
+            // next point must not list them either.
+            if self.discovered_metrics.contains(name) {
+                self.pruned.insert(name.clone());
+            }
+        }
+        let before = self.discovered_metrics.len();
+        self.discovered_metrics.retain(|name| !stale.contains(name));
+
+        self.reselect(highlighted);
+        let removed = before - self.discovered_metrics.len();
+        if removed > 0 {
+            self.flash(format!("Removed {} stale metric(s)", removed));
+        }
+    }
+
+    /// The `--alias` of a metric if it has one, otherwise its name.
+    fn display_name<'a>(&'a self, metric_name: &'a str) -> &'a str {
+        self.aliases.get(metric_name).map_or(metric_name, String::as_str)
+    }
+
+    /// `[ip] ` for `--show-source`; Unix sockets have no peer address, so they show as `local`.
+    /// With several listeners, `[→addr] ` names the one the update arrived on.
+    fn source_prefix(&self, source: Option<SocketAddr>, listener: Option<SocketAddr>) -> Option<String> {
+        let source = self.show_source.then(|| {
+            format!("[{}] ", source.map_or("local".to_string(), |addr| addr.ip().to_string()))
+        });
+        let listener = listener.filter(|_| self.show_listener).map(|addr| format!("[→{}] ", addr));
+        match (source, listener) {
+            (None, None) => None,
+            (source, listener) => Some(source.unwrap_or_default() + &listener.unwrap_or_default()),
+        }
+    }
+
+    /// Records an update both in the all-metrics pane and in the metric's own
+    /// buffer, so re-selecting a metric shows what it received earlier.
+    fn add_update(&mut self, metric_name: String, update: String, prefix: Option<String>) {
+        let update = match prefix {
+            Some(prefix) => prefix + &update,
+            None => update,
+        };
+        let now = Instant::now();
+        // A pruned metric only gets its own buffers back once a point lists it
+        // again; until then its updates go to the shared log alone.
+        let listed = !self.pruned.contains(&metric_name);
+        let times = [
+            Some(&mut self.update_times),
+            listed.then(|| self.metric_update_times.entry(metric_name.clone()).or_default()),
+        ];
+        for times in times.into_iter().flatten() {
+            times.push_back(now);
+            while times.front().is_some_and(|t| now.duration_since(*t) > self.update_window) {
+                times.pop_front();
+            }
+        }
+        let buffers = [
+            Some(&mut self.recent_updates),
+            listed.then(|| self.metric_updates.entry(metric_name).or_default()),
+        ];
+        for buffer in buffers.into_iter().flatten() {
+            buffer.push_front(update.clone());
+            if buffer.len() > MAX_UPDATES {
+                buffer.pop_back();
+            }
+        }
+    }
+
+    /// Updates received within `update_window` for the pane's metric, or for
+    /// all of them. Times are only pruned on arrival, so a quiet metric's
+    /// leftovers are filtered here.
+    fn windowed_update_count(&self) -> usize {
+        let times = match &self.selected_metric {
+            Some(metric) => self.metric_update_times.get(metric),
+            None => Some(&self.update_times),
+        };
+        times.map_or(0, |times| {
+            times.iter().rev().take_while(|t| t.elapsed() <= self.update_window).count()
+        })
+    }
+
+    /// Updates shown in the pane: the selected metric's own, or everyone's.
+    fn visible_updates(&self) -> Option<&VecDeque<String>> {
+        match &self.selected_metric {
+            Some(metric) => self.metric_updates.get(metric),
+            None => Some(&self.recent_updates),
+        }
+    }
+
+    fn toggle_wrap_updates(&mut self) {
+        self.wrap_updates = !self.wrap_updates;
+    }
+
+    /// The updates pane: one row per update, cut off with an ellipsis, or
+    /// wrapped over as many rows as each needs.
+    fn render_updates(&self, area: Rect, frame: &mut Frame) {
+        let updates_title = match &self.selected_metric {
+            Some(metric) => format!("Recent Updates (Filtered: {})", metric),
+            None => "Recent Updates (All Metrics)".to_string(),
+        };
+        let updates_title = format!(
+            "{} [{} in {}s] [w: {}]",
+            updates_title,
+            self.windowed_update_count(),
+            self.update_window.as_secs(),
+            if self.wrap_updates { "truncate" } else { "wrap" }
+        );
+        let block = Block::default().title(updates_title).borders(Borders::ALL);
+        let updates = self.visible_updates().into_iter().flatten();
+
+        if self.wrap_updates {
+            // Newest first, so whatever doesn't fit falls off the bottom.
+            let lines: Vec<Line> = updates
+                .take(usize::from(area.height))
+                .map(|u| Line::from(u.as_str()))
+                .collect();
+            frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(block), area);
+        } else {
+            let width = usize::from(area.width.saturating_sub(2));
+            let items: Vec<ListItem> = updates.map(|u| ListItem::new(ellipsize(u, width))).collect();
+            frame.render_widget(List::new(items).block(block), area);
+        }
+    }
+
+    fn clear_updates(&mut self) {
+        self.recent_updates.clear();
+        self.metric_updates.clear();
+        self.update_times.clear();
+        self.metric_update_times.clear();
+    }
+
+    /// The metrics shown in the list, in list order.
+    fn listed_metrics(&self) -> Vec<&String> {
+        self.discovered_metrics
+            .iter()
+            .filter(|m| self.kind_filter.admits(self.metric_info.get(*m).and_then(|info| info.kind)))
+            .collect()
+    }
+
+    /// Highlights `metric` if it is listed, otherwise the row at the same
+    /// position (or the last row), so the highlight doesn't jump needlessly.
+    fn reselect(&mut self, metric: Option<String>) {
+        let listed = self.listed_metrics();
+        let index = metric
+            .and_then(|name| listed.iter().position(|m| **m == name))
+            .or_else(|| {
+                let last = listed.len().checked_sub(1)?;
+                Some(self.list_state.selected().unwrap_or(0).min(last))
+            });
+        self.list_state.select(index);
+    }
+
+    /// Switches between sorting by name and arrival order, keeping the same
+    /// metric highlighted.
+    fn cycle_sort_mode(&mut self) {
+        let highlighted = self.highlighted_metric().cloned();
+        self.sort_mode = self.sort_mode.next();
+        match self.sort_mode {
+            SortMode::Name => self.discovered_metrics.sort(),
+            SortMode::Arrival => {
+                let arrival = &self.arrival;
+                self.discovered_metrics.sort_by_key(|name| arrival.get(name).copied());
+            }
+        }
+        self.reselect(highlighted);
+        self.flash(format!("Sorted by {}", self.sort_mode.label()));
+    }
+
+    fn cycle_kind_filter(&mut self) {
+        let highlighted = self.highlighted_metric().cloned();
+        self.kind_filter = self.kind_filter.next();
+        self.reselect(highlighted);
+    }
+
+    fn next(&mut self) {
+        let len = self.listed_metrics().len();
+        if len == 0 {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i >= len - 1 {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    fn previous(&mut self) {
+        let len = self.listed_metrics().len();
+        if len == 0 {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    len - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    fn select_first(&mut self) {
+        if !self.listed_metrics().is_empty() {
+            self.list_state.select(Some(0));
+        }
+    }
+
+    fn select_last(&mut self) {
+        let len = self.listed_metrics().len();
+        if len > 0 {
+            self.list_state.select(Some(len - 1));
+        }
+    }
+
+    /// Handles the `g` prefix: `gg` jumps to the top and `g<number>` to the
+    /// 1-based position typed so far. Returns `true` when the key was consumed;
+    /// any other key cancels the pending jump.
+    fn handle_jump_key(&mut self, code: KeyCode) -> bool {
+        let Some(pending) = self.pending_jump.as_mut() else {
+            if code == KeyCode::Char('g') {
+                self.pending_jump = Some(String::new());
+                return true;
+            }
+            return false;
+        };
+
+        match code {
+            KeyCode::Char('g') if pending.is_empty() => {
+                self.pending_jump = None;
+                self.select_first();
+                true
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                pending.push(c);
+                if let Ok(position) = pending.parse::<usize>() {
+                    if (1..=self.listed_metrics().len()).contains(&position) {
+                        self.list_state.select(Some(position - 1));
+                    }
+                }
+                true
+            }
+            _ => {
+                self.pending_jump = None;
+                false
+            }
+        }
+    }
+
+    /// Handles keys while the `:` command line is open (or opens it). Returns
+    /// `true` when the key was consumed.
+    fn handle_command_key(&mut self, code: KeyCode) -> bool {
+        let Some(line) = self.command_line.as_mut() else {
+            if code == KeyCode::Char(':') {
+                self.command_line = Some(String::new());
+                return true;
+            }
+            return false;
+        };
+
+        match code {
+            KeyCode::Char(c) => line.push(c),
+            KeyCode::Backspace if !line.is_empty() => {
+                line.pop();
+            }
+            KeyCode::Backspace | KeyCode::Esc => self.command_line = None,
+            KeyCode::Enter => {
+                let input = self.command_line.take().unwrap_or_default();
+                if !input.trim().is_empty() {
+                    match input.parse::<Command>() {
+                        Ok(command) => self.run_command(command),
+                        Err(e) => self.flash(e),
+                    }
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Highlights and graphs `name`, listing every kind again if the current
+    /// filter hides it.
+    fn show_metric(&mut self, name: String) {
+        if !self.listed_metrics().contains(&&name) {
+            self.kind_filter = KindFilter::All;
+        }
+        self.reselect(Some(name.clone()));
+        if self.selected_metric.as_ref() != Some(&name) {
+            self.toggle_selected_metric();
+        }
+    }
+
+    /// Every discovered metric matching the finder query, best first. A
+    /// metric matches by its name or its alias.
+    fn finder_matches(&self, query: &str) -> Vec<&String> {
+        let mut matches: Vec<(i64, &String)> = self
+            .discovered_metrics
+            .iter()
+            .filter_map(|name| {
+                let alias = self.aliases.get(name).and_then(|alias| fuzzy::score(query, alias));
+                Some((fuzzy::score(query, name).max(alias)?, name))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        matches.into_iter().map(|(_, name)| name).collect()
+    }
+
+    /// Handles keys while the `/` finder is open (or opens it). Returns `true`
+    /// when the key was consumed.
+    fn handle_finder_key(&mut self, code: KeyCode) -> bool {
+        let Some(query) = self.finder.as_ref().map(|finder| finder.query.clone()) else {
+            if code == KeyCode::Char('/') {
+                self.finder = Some(Finder::default());
+                return true;
+            }
+            return false;
+        };
+        let count = self.finder_matches(&query).len();
+        let finder = self.finder.get_or_insert_with(Finder::default);
+
+        match code {
+            KeyCode::Char(c) => {
+                finder.query.push(c);
+                finder.highlighted = 0;
+            }
+            KeyCode::Backspace => {
+                finder.query.pop();
+                finder.highlighted = 0;
+            }
+            KeyCode::Up => finder.highlighted = finder.highlighted.saturating_sub(1),
+            KeyCode::Down => finder.highlighted = (finder.highlighted + 1).min(count.saturating_sub(1)),
+            KeyCode::Esc => self.finder = None,
+            KeyCode::Enter => {
+                let highlighted = finder.highlighted;
+                self.finder = None;
+                match self.finder_matches(&query).get(highlighted) {
+                    Some(&name) => self.show_metric(name.clone()),
+                    None => self.flash(format!("No metric matches '{}'", query)),
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn render_finder(&self, finder: &Finder, area: Rect, frame: &mut Frame) {
+        let area = centered_rect(60, 60, area);
+        let matches = self.finder_matches(&finder.query);
+        // Borders and the query line take three rows; the rest scrolls with the highlight.
+        let rows = usize::from(area.height.saturating_sub(3));
+        let first = (finder.highlighted + 1).saturating_sub(rows);
+        let mut lines = vec![Line::from(format!("> {}", finder.query))];
+        for (i, name) in matches.iter().enumerate().skip(first).take(rows) {
+            let label = match self.aliases.get(*name) {
+                Some(alias) => format!("{} ({})", alias, name),
+                None => name.to_string(),
+            };
+            let style = if i == finder.highlighted {
+                Style::default().bg(Color::White).fg(Color::Black)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::styled(label, style));
+        }
+
+        let popup = Paragraph::new(lines).block(
+            Block::default()
+                .title(format!(
+                    "Find Metric ({}/{}) [Enter: show, Up/Down: move, Esc: close]",
+                    matches.len(),
+                    self.discovered_metrics.len()
+                ))
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Select(name) => {
+                if !self.discovered_metrics.contains(&name) {
+                    self.flash(format!("No metric named {}", name));
+                    return;
+                }
+                self.show_metric(name);
+            }
+            Command::Theme(name) => match ThemeChoice::named(&name) {
+                Some(choice) => {
+                    self.theme = Theme::chosen(choice);
+                    self.theme_choice = choice;
+                    self.flash(format!("Theme: {}", name));
+                }
+                None => self.flash(format!("Unknown theme '{}'; expected auto, dark or light", name)),
+            },
+            Command::History(points) => {
+                self.history = points;
+                // Metrics with a `--history-for` keep theirs.
+                let history_for = &self.history_for;
+                let kept = |name: &str| history_limit(history_for, points, name);
+                for (name, series) in self.metric_data.iter_mut().chain(self.running_totals.iter_mut()) {
+                    let excess = series.len().saturating_sub(kept(name));
+                    series.drain(..excess);
+                }
+                for (name, columns) in &mut self.histogram_columns {
+                    let excess = columns.len().saturating_sub(kept(name));
+                    columns.drain(..excess);
+                }
+                self.flash(format!("Keeping {} points per metric", points));
+            }
+            Command::Export(ExportFormat::Csv) => self.export_selected_csv(),
+            Command::Export(ExportFormat::Markdown) => self.export_markdown_report(),
+        }
+    }
+
+    fn toggle_selected_metric(&mut self) {
+        if let Some(index) = self.list_state.selected() {
+            if let Some(metric) = self.listed_metrics().get(index).copied() {
+                // The list is hidden behind a compact-mode graph, so Enter always goes back.
+                let deselect = self.selected_metric.as_ref() == Some(metric)
+                    || (self.compact && self.selected_metric.is_some());
+                if deselect {
+                    self.selected_metric = None;
+                    self.show_graph = false;
+                } else {
+                    self.selected_metric = Some(metric.clone());
+                    self.show_graph = self.default_view == DefaultView::Graph;
+                }
+                if self.clear_updates_on_select {
+                    self.clear_updates();
+                }
+            }
+        }
+    }
+
+    /// Switches the selected metric's pane between its graph and the updates.
+    fn toggle_view(&mut self) {
+        if self.selected_metric.is_none() {
+            self.flash("Select a metric to switch between its graph and updates");
+            return;
+        }
+        self.show_graph = !self.show_graph;
+    }
+
+    fn toggle_compact(&mut self) {
+        self.compact = !self.compact;
+    }
+
+    /// A compact trend of the last few points, drawn with unicode block characters.
+    fn sparkline(&self, metric_name: &str) -> String {
+        let Some(points) = self.metric_data.get(metric_name) else {
+            return String::new();
+        };
+        let recent: Vec<f64> = points
+            .iter()
+            .skip(points.len().saturating_sub(SPARKLINE_POINTS))
+            .map(|p| p.value)
+            .filter(|v| v.is_finite())
+            .collect();
+
+        let min = recent.iter().copied().reduce(f64::min).unwrap_or(0.0);
+        let max = recent.iter().copied().reduce(f64::max).unwrap_or(0.0);
+        let span = max - min;
+        recent
+            .iter()
+            .map(|v| {
+                let level = if span > 0.0 {
+                    ((v - min) / span * (SPARKLINE_BARS.len() - 1) as f64).round() as usize
+                } else {
+                    0
+                };
+                SPARKLINE_BARS[level.min(SPARKLINE_BARS.len() - 1)]
+            })
+            .collect()
+    }
+
+    /// `[delta]`/`[cumulative]` for sums so the shape of the line makes sense.
+    fn temporality_label(&self, metric_name: &str) -> String {
+        self.metric_info
+            .get(metric_name)
+            .and_then(|info| info.temporality)
+            .map(|t| format!("[{}] ", t.label()))
+            .unwrap_or_default()
+    }
+
+    /// `{scope} ` when scope annotations are on and the metric's scope is known.
+    fn scope_label(&self, metric_name: &str) -> String {
+        if !self.show_scope {
+            return String::new();
+        }
+        self.metric_info
+            .get(metric_name)
+            .and_then(|info| info.scope.as_ref())
+            .map(|scope| format!("{{{}}} ", scope))
+            .unwrap_or_default()
+    }
+
+    /// `(N pts)`, or `(MAX+)` once the history is full and old points are dropped.
+    fn point_count_label(&self, metric_name: &str) -> String {
+        let count = self.metric_data.get(metric_name).map_or(0, VecDeque::len);
+        let history = self.history_of(metric_name);
+        if count >= history {
+            format!("({}+)", history)
+        } else {
+            format!("({} pts)", count)
+        }
+    }
+
+    /// ` [N sets]` for metrics above `--cardinality-warn`.
+    fn cardinality_label(&self, metric_name: &str) -> String {
+        let count = self.metric_attributes.get(metric_name).map_or(0, BTreeSet::len);
+        match self.cardinality_warn {
+            Some(limit) if count > limit => format!(" [{} sets]", count),
+            _ => String::new(),
+        }
+    }
+
+    /// Metrics by descending number of distinct attribute sets, then by name.
+    fn cardinalities(&self) -> Vec<(&String, usize)> {
+        let mut counts: Vec<(&String, usize)> = self
+            .discovered_metrics
+            .iter()
+            .map(|name| (name, self.metric_attributes.get(name).map_or(0, BTreeSet::len)))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        counts
+    }
+
+    fn high_cardinality_count(&self) -> usize {
+        let Some(limit) = self.cardinality_warn else {
+            return 0;
+        };
+        self.metric_attributes.values().filter(|sets| sets.len() > limit).count()
+    }
+
+    /// Shows a short-lived message in the status bar.
+    fn flash(&mut self, message: impl Into<String>) {
+        self.status_message = Some((message.into(), Instant::now()));
+    }
+
+    fn clear_data(&mut self) {
+        self.discovered_metrics.clear();
+        self.arrival.clear();
+        self.metric_data.clear();
+        self.metric_info.clear();
+        self.metric_attributes.clear();
+        self.metric_listeners.clear();
+        self.histogram_columns.clear();
+        self.alerting.clear();
+        self.overlay_metrics.clear();
+        self.locked_y_bounds.clear();
+        self.rate_overrides.clear();
+        self.running_totals.clear();
+        self.first_points.clear();
+        self.rejected_series = 0;
+        self.pruned.clear();
+        self.clear_updates();
+        self.selected_metric = None;
+        self.show_graph = false;
+        self.list_state.select(None);
+        self.flash("Cleared all collected data");
+    }
+
+    fn export_selected_csv(&mut self) {
+        let Some(metric_name) = self.selected_metric.clone() else {
+            self.flash("Select a metric with Enter before exporting");
+            return;
+        };
+        let points = self.metric_data.get(&metric_name).into_iter().flatten();
+        match export::write_csv(&metric_name, points) {
+            Ok(path) => self.flash(format!("Exported {} to {}", metric_name, path.display())),
+            Err(e) => self.flash(format!("CSV export failed: {}", e)),
+        }
+    }
+
+    /// Writes every discovered metric with its min/max/avg/latest value to a
+    /// Markdown file, for pasting into incident tickets.
+    fn export_markdown_report(&mut self) {
+        let rows: Vec<Vec<String>> = self
+            .discovered_metrics
+            .iter()
+            .map(|name| {
+                let points = self.metric_data.get(name);
+                let info = self.metric_info.get(name);
+                let unit = info.map_or("", |i| i.unit.as_str());
+                let stats = points.and_then(SeriesStats::of);
+                let value = |f: fn(&SeriesStats) -> f64| {
+                    stats.as_ref().map_or("-".to_string(), |s| format_value(f(s), unit, self.precision))
+                };
+                vec![
+                    name.clone(),
+                    info.and_then(|i| i.kind).map_or("-", |k| k.label()).to_string(),
+                    points.map_or(0, VecDeque::len).to_string(),
+                    value(|s| s.min),
+                    value(|s| s.max),
+                    value(|s| s.avg),
+                    value(|s| s.last),
+                    stats
+                        .and_then(|s| self.timezone.format(s.last_timestamp as i64, "%Y-%m-%d %H:%M:%S %Z"))
+                        .unwrap_or_else(|| "-".to_string()),
+                ]
+            })
+            .collect();
+        let title = format!(
+            "otel-dashboard snapshot {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S %Z")
+        );
+        let header = ["Metric", "Type", "Points", "Min", "Max", "Avg", "Latest", "Latest at"];
+        match export::write_markdown_report(&title, &header, &rows) {
+            Ok(path) => self.flash(format!("Wrote {} metrics to {}", rows.len(), path.display())),
+            Err(e) => self.flash(format!("Report export failed: {}", e)),
+        }
+    }
+
+    /// Gives the list `SPLIT_STEP` more or less of the height.
+    fn adjust_split(&mut self, grow_list: bool) {
+        self.split = if grow_list {
+            (self.split + SPLIT_STEP).min(MAX_SPLIT)
+        } else {
+            self.split.saturating_sub(SPLIT_STEP).max(MIN_SPLIT)
+        };
+        self.flash(format!("List: {}% of the height", self.split));
+    }
+
+    /// Doubles (`+`) or halves (`-`) the poll/draw interval within sane limits.
+    fn adjust_refresh_interval(&mut self, slower: bool) {
+        let interval = if slower {
+            self.refresh_interval * 2
+        } else {
+            self.refresh_interval / 2
+        };
+        self.refresh_interval = interval.clamp(MIN_REFRESH_INTERVAL, MAX_REFRESH_INTERVAL);
+    }
+
+    fn status_line(&self) -> String {
+        let uptime = self.started_at.elapsed().as_secs();
+        let total_points: usize = self.metric_data.values().map(VecDeque::len).sum();
+        let mut line = format!(
+            " {} | Up: {:02}:{:02}:{:02} | Metrics: {} | Points: {} | RPS: {} | Refresh: {}ms",
+            self.listening,
+            uptime / 3600,
+            uptime / 60 % 60,
+            uptime % 60,
+            self.discovered_metrics.len(),
+            total_points,
+            self.stats.requests_per_second,
+            self.refresh_interval.as_millis()
+        );
+        if self.stats.nonfinite_values > 0 {
+            line.push_str(&format!(" | NaN/Inf dropped: {}", self.stats.nonfinite_values));
+        }
+        if self.stats.duplicate_points > 0 {
+            line.push_str(&format!(" | Duplicates skipped: {}", self.stats.duplicate_points));
+        }
+        if self.stats.empty_requests > 0 {
+            line.push_str(&format!(" | Empty requests: {}", self.stats.empty_requests));
+        }
+        if let Some((limit, counts)) = self.stats.rate_limit {
+            line.push_str(&format!(
+                " | Limit {}/s: {} accepted, {} rejected",
+                limit, counts.accepted, counts.rejected
+            ));
+        }
+        if self.rejected_series > 0 {
+            line.push_str(&format!(
+                " | Series limit {} reached, {} dropped",
+                self.max_series, self.rejected_series
+            ));
+        }
+        let high_cardinality = self.high_cardinality_count();
+        if high_cardinality > 0 {
+            line.push_str(&format!(" | High cardinality: {} (C)", high_cardinality));
+        }
+        if let Some(pending) = &self.pending_jump {
+            line.push_str(&format!(" | jump: g{}", pending));
+        }
+        if let Some((message, shown_at)) = &self.status_message {
+            if shown_at.elapsed() < STATUS_MESSAGE_TTL {
+                line.push_str(" | ");
+                line.push_str(message);
+            }
+        }
+        line
+    }
+
+    /// Plain-text table of every discovered metric and its latest value, for `--watch`.
+    fn summary_table(&self) -> String {
+        let rows: Vec<[String; 5]> = self
+            .discovered_metrics
+            .iter()
+            .map(|name| {
+                let points = self.metric_data.get(name);
+                let latest = points
+                    .and_then(VecDeque::back)
+                    .map_or("-".to_string(), |p| format_decimal(p.value, self.decimals()));
+                let info = self.metric_info.get(name);
+                let unit = info.map_or("", |i| i.unit.as_str());
+                [
+                    name.clone(),
+                    latest,
+                    points.map_or(0, VecDeque::len).to_string(),
+                    if unit.is_empty() { "-".to_string() } else { unit.to_string() },
+                    info.and_then(|i| i.kind).map_or("-", |k| k.label()).to_string(),
+                ]
+            })
+            .collect();
+
+        let header = ["METRIC", "LATEST", "POINTS", "UNIT", "TYPE"];
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let format_row = |cells: [&str; 5]| {
+            format!(
+                "{:<w0$}  {:>w1$}  {:>w2$}  {:<w3$}  {}",
+                cells[0],
+                cells[1],
+                cells[2],
+                cells[3],
+                cells[4],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+                w3 = widths[3],
+            )
+        };
+
+        let mut table = format_row(header);
+        table.push('\n');
+        for row in &rows {
+            table.push_str(format_row(row.each_ref().map(String::as_str)).trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    fn toggle_quantiles(&mut self) {
+        self.show_quantiles = !self.show_quantiles;
+    }
+
+    /// Series to plot for a metric: the p50/p90/p99 lines when the metric is a
+    /// histogram and quantile view is on, otherwise the metric's own points.
+    fn graph_series(&self, metric_name: &str) -> Vec<GraphSeries> {
+        let color = self.theme.series_color(metric_name);
+        let mut series = self.metric_series(metric_name, color, self.show_quantiles);
+
+        for overlay in self.overlay_metrics.iter().filter(|m| m.as_str() != metric_name) {
+            series.extend(self.metric_series(overlay, self.theme.series_color(overlay), false));
+        }
+
+        if self.log_scale {
+            for s in &mut series {
+                s.log_scale();
+            }
+        }
+        if self.normalize {
+            for s in &mut series {
+                s.normalize(self.decimals());
+            }
+        }
+        series
+    }
+
+    /// The quantile lines when `quantiles` is set and the metric is a
+    /// histogram or summary, otherwise the metric's own points.
+    fn metric_series(&self, metric_name: &str, color: Color, quantiles: bool) -> Vec<GraphSeries> {
+        let since = self.window_start();
+        let to_data = |points: &VecDeque<MetricPoint>| -> Vec<(f64, f64)> {
+            points
+                .iter()
+                .filter(|point| point.value.is_finite() && point.timestamp >= since)
+                .map(|point| (point.timestamp as f64, point.value))
+                .collect()
+        };
+
+        if quantiles {
+            // Histograms derive p50/p90/p99; summaries bring whichever quantiles they export.
+            let prefix = quantile_series_name(metric_name, "");
+            let prefix = prefix.trim_end_matches(']');
+            let mut found: Vec<(f64, &str, &VecDeque<MetricPoint>)> = self
+                .metric_data
+                .iter()
+                .filter_map(|(name, points)| {
+                    let label = name.strip_prefix(prefix)?.strip_suffix(']')?;
+                    let percentile = label.strip_prefix('p')?.parse::<f64>().ok()?;
+                    Some((percentile, label, points))
+                })
+                .collect();
+            found.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let quantiles: Vec<_> = found
+                .into_iter()
+                .zip(QUANTILE_COLORS.into_iter().cycle())
+                .map(|((_, label, points), color)| GraphSeries {
+                    name: label.to_string(),
+                    color,
+                    data: to_data(points),
+                })
+                .collect();
+            if !quantiles.is_empty() {
+                return quantiles;
+            }
+        }
 
-const MAX_POINTS: usize = 100;
+        if self.accumulates(metric_name) {
+            if let Some(totals) = self.running_totals.get(metric_name) {
+                return vec![GraphSeries {
+                    name: metric_name.to_string(),
+                    color,
+                    data: to_data(totals),
+                }];
+            }
+        }
 
-pub struct TuiState {
-    discovered_metrics: Vec<String>,
-    recent_updates: VecDeque<String>,
-    list_state: ListState,
-    selected_metric: Option<String>,
-    metric_data: HashMap<String, VecDeque<MetricPoint>>,
-    show_graph: bool,
-}
+        self.metric_data
+            .get(metric_name)
+            .map(|points| {
+                let mut data = to_data(points);
+                if self.shows_rate(metric_name) {
+                    let info = self.metric_info.get(metric_name);
+                    let temporality = info.and_then(|info| info.temporality);
+                    // Only the series' very first point counts from the start time;
+                    // `--cumulative-to-delta` drops that point anyway.
+                    let first = self.first_points.get(metric_name).map(|&t| t as f64);
+                    let start = info
+                        .and_then(|info| info.start_time)
+                        .filter(|_| temporality != Some(Temporality::CumulativeAsDelta))
+                        .filter(|_| first.is_some() && data.first().map(|p| p.0) == first);
+                    let cumulative = temporality == Some(Temporality::Cumulative);
+                    data = Self::per_second(&data, cumulative, start.map(|s| s as f64));
+                }
+                vec![GraphSeries {
+                    name: metric_name.to_string(),
+                    color,
+                    data,
+                }]
+            })
+            .unwrap_or_default()
+    }
 
-impl TuiState {
-    fn new() -> Self {
-        Self {
-            discovered_metrics: Vec::new(),
-            recent_updates: VecDeque::with_capacity(100),
-            list_state: ListState::default(),
-            selected_metric: None,
-            metric_data: HashMap::new(),
-            show_graph: false,
+    /// Whether a sum is graphed as a per-second rate. Counters are by default,
+    /// since their raw values only ever climb; up-down counters aren't.
+    fn shows_rate(&self, metric_name: &str) -> bool {
+        match self.metric_info.get(metric_name).and_then(|info| info.monotonic) {
+            Some(monotonic) => monotonic != self.rate_overrides.contains(metric_name),
+            None => false,
         }
     }
 
-    fn add_metric(&mut self, metric: String) {
-        if !self.discovered_metrics.contains(&metric) {
-            self.discovered_metrics.push(metric.clone());
-            self.discovered_metrics.sort();
-            self.metric_data
-                .insert(metric, VecDeque::with_capacity(MAX_POINTS));
-            if self.list_state.selected().is_none() {
-                self.list_state.select(Some(0));
-            }
+    /// Whether `metric_name` is a sum whose points are deltas, as received or
+    /// made by `--cumulative-to-delta`.
+    fn is_delta_sum(&self, metric_name: &str) -> bool {
+        self.metric_info.get(metric_name).is_some_and(|info| {
+            matches!(info.temporality, Some(Temporality::Delta | Temporality::CumulativeAsDelta))
+        })
+    }
+
+    /// Whether a delta sum is graphed as its running total, which takes
+    /// precedence over the rate view.
+    fn accumulates(&self, metric_name: &str) -> bool {
+        self.accumulate && self.is_delta_sum(metric_name)
+    }
+
+    fn toggle_accumulate(&mut self) {
+        self.accumulate = !self.accumulate;
+        // Locked bounds are in the old value space.
+        self.locked_y_bounds.clear();
+        self.flash(if self.accumulate {
+            "Delta sums: running total"
+        } else {
+            "Delta sums: as received"
+        });
+    }
+
+    fn toggle_rate(&mut self) {
+        let Some(metric) = self.selected_metric.clone() else {
+            self.flash("Select a sum to switch between rate and raw values");
+            return;
+        };
+        if self.metric_info.get(&metric).and_then(|info| info.monotonic).is_none() {
+            self.flash(format!("{} is not a sum; only sums have a rate view", metric));
+            return;
         }
+        if !self.rate_overrides.remove(&metric) {
+            self.rate_overrides.insert(metric.clone());
+        }
+        // The values change scale, so a locked range no longer fits.
+        self.locked_y_bounds.remove(&metric);
+        let view = if self.shows_rate(&metric) { "per-second rate" } else { "raw values" };
+        self.flash(format!("{}: {}", metric, view));
     }
 
-    fn add_metric_point(&mut self, name: String, point: MetricPoint) {
-        if let Some(points) = self.metric_data.get_mut(&name) {
-            points.push_back(point);
-            if points.len() > MAX_POINTS {
-                points.pop_front();
+    /// Per-second increase between consecutive points. Cumulative values are
+    /// diffed first, taking a drop as a counter reset to zero; deltas already
+    /// are increases. Points within the same second are folded into the next.
+    /// With the series' `start` time, the first point is rated too, as its
+    /// value over the time since the start.
+    fn per_second(data: &[(f64, f64)], cumulative: bool, start: Option<f64>) -> Vec<(f64, f64)> {
+        let mut rates = Vec::with_capacity(data.len());
+        let Some(&(mut previous_t, mut previous_v)) = data.first() else {
+            return rates;
+        };
+        if let Some(start) = start.filter(|&start| previous_t > start) {
+            rates.push((previous_t, previous_v / (previous_t - start)));
+        }
+        let mut pending = 0.0;
+        for &(t, v) in &data[1..] {
+            let elapsed = t - previous_t;
+            if cumulative {
+                if elapsed <= 0.0 {
+                    continue;
+                }
+                let increase = if v >= previous_v { v - previous_v } else { v };
+                rates.push((t, increase / elapsed));
+            } else {
+                pending += v;
+                if elapsed <= 0.0 {
+                    continue;
+                }
+                rates.push((t, pending / elapsed));
+                pending = 0.0;
             }
+            previous_t = t;
+            previous_v = v;
         }
+        rates
     }
 
-    fn add_update(&mut self, update: String) {
-        if let Some(selected) = &self.selected_metric {
-            if update.starts_with(selected) {
-                self.recent_updates.push_front(update);
-                if self.recent_updates.len() > 100 {
-                    self.recent_updates.pop_back();
-                }
+    /// Adds or removes the highlighted metric from the graph overlay.
+    fn toggle_overlay(&mut self) {
+        if let Some(metric) = self.highlighted_metric().cloned() {
+            if !self.overlay_metrics.remove(&metric) {
+                self.overlay_metrics.insert(metric);
             }
+        }
+    }
+
+    /// Decimals for plain numbers, `--precision` or the default.
+    fn decimals(&self) -> usize {
+        self.precision.unwrap_or(DEFAULT_PRECISION)
+    }
+
+    fn toggle_normalize(&mut self) {
+        self.normalize = !self.normalize;
+        // Locked bounds are in the old value space.
+        self.locked_y_bounds.clear();
+    }
+
+    /// Where the metric's `--threshold` falls on the y-axis: on the log10 scale
+    /// when that is on, and nowhere for normalized graphs, whose y-axis has no
+    /// units.
+    fn threshold_y(&self, metric_name: &str) -> Option<f64> {
+        let value = *self.thresholds.get(metric_name)?;
+        if self.normalize {
+            None
+        } else if self.log_scale {
+            (value > 0.0).then(|| value.log10())
         } else {
-            self.recent_updates.push_front(update);
-            if self.recent_updates.len() > 100 {
-                self.recent_updates.pop_back();
-            }
+            Some(value)
         }
     }
 
-    fn next(&mut self) {
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i >= self.discovered_metrics.len() - 1 {
-                    0
-                } else {
-                    i + 1
-                }
+    /// The padded y range that auto-fits every finite point of `series`.
+    fn fitted_y_bounds(series: &[GraphSeries]) -> Option<[f64; 2]> {
+        let mut values = series.iter().flat_map(|s| s.data.iter()).map(|p| p.1).filter(|y| y.is_finite());
+        let first = values.next()?;
+        let (min, max) = values.fold((first, first), |(min, max), y| (min.min(y), max.max(y)));
+        Some(padded_bounds(min, max))
+    }
+
+    /// Pins the selected metric's graph to its current y range, or resumes
+    /// auto-fitting if it is already pinned.
+    fn toggle_y_lock(&mut self) {
+        let Some(metric) = self.selected_metric.clone() else {
+            self.flash("Select a metric with Enter before locking its y-axis");
+            return;
+        };
+        if self.locked_y_bounds.remove(&metric).is_some() {
+            self.flash(format!("Y-axis of {} auto-fits again", metric));
+            return;
+        }
+        match Self::fitted_y_bounds(&self.graph_series(&metric)) {
+            Some(bounds) => {
+                self.flash(format!(
+                    "Y-axis of {} locked to {}..{}",
+                    metric,
+                    format_decimal(bounds[0], self.decimals()),
+                    format_decimal(bounds[1], self.decimals())
+                ));
+                self.locked_y_bounds.insert(metric, bounds);
             }
-            None => 0,
+            None => self.flash(format!("{} has no points to lock the y-axis to", metric)),
+        }
+    }
+
+    /// `[` narrows the graph to a shorter recent window, `]` widens it back
+    /// towards the whole retained history.
+    fn adjust_time_window(&mut self, grow: bool) {
+        self.time_window = if grow {
+            (self.time_window + 1).min(TIME_WINDOWS.len() - 1)
+        } else {
+            self.time_window.saturating_sub(1)
         };
-        self.list_state.select(Some(i));
     }
 
-    fn previous(&mut self) {
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.discovered_metrics.len() - 1
-                } else {
-                    i - 1
-                }
-            }
+    /// Oldest timestamp (epoch seconds) shown in the graph.
+    fn window_start(&self) -> u64 {
+        match TIME_WINDOWS[self.time_window] {
+            Some(window) => self.now_secs().saturating_sub(window.as_secs()),
             None => 0,
+        }
+    }
+
+    /// Timestamps of the first and last graphed point of `metric`.
+    fn x_extent(&self, metric: &str) -> Option<(f64, f64)> {
+        let series = self.graph_series(metric);
+        let mut xs = series.iter().flat_map(|s| s.data.iter()).map(|p| p.0).filter(|x| x.is_finite());
+        let first = xs.next()?;
+        Some(xs.fold((first, first), |(min, max), x| (min.min(x), max.max(x))))
+    }
+
+    /// The slice of `[min_x, max_x]` the zoom and pan select.
+    fn x_view(&self, min_x: f64, max_x: f64) -> [f64; 2] {
+        let span = (max_x - min_x) / f64::from(1u32 << self.zoom);
+        let end = self.view_end.map_or(max_x, |end| end.clamp(min_x + span, max_x));
+        [end - span, end]
+    }
+
+    fn adjust_zoom(&mut self, zoom_in: bool) {
+        self.zoom = if zoom_in { (self.zoom + 1).min(MAX_ZOOM) } else { self.zoom.saturating_sub(1) };
+        if self.zoom == 0 {
+            self.view_end = None;
+        }
+    }
+
+    /// Moves the view a quarter of its width. Panning past the latest point
+    /// goes back to following it.
+    fn pan(&mut self, forward: bool) {
+        let Some(metric) = self.selected_metric.clone() else {
+            return;
         };
-        self.list_state.select(Some(i));
+        let Some((min_x, max_x)) = self.x_extent(&metric) else {
+            return;
+        };
+        let [start, end] = self.x_view(min_x, max_x);
+        let step = (end - start) / 4.0;
+        let end = if forward { end + step } else { end - step };
+        self.view_end = (end < max_x).then_some(end.max(min_x));
     }
 
-    fn toggle_selected_metric(&mut self) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some(metric) = self.discovered_metrics.get(index) {
-                if self.selected_metric.as_ref().map_or(false, |m| m == metric) {
-                    self.selected_metric = None;
-                    self.show_graph = false;
-                    self.recent_updates.clear();
+    fn follow_live(&mut self) {
+        self.zoom = 0;
+        self.view_end = None;
+    }
+
+    /// `, 4x` and `, paused` for a zoomed or panned graph.
+    fn view_label(&self) -> String {
+        let mut label = String::new();
+        if self.zoom > 0 {
+            label.push_str(&format!(", {}x", 1u32 << self.zoom));
+        }
+        if self.view_end.is_some() {
+            label.push_str(", paused");
+        }
+        label
+    }
+
+    fn time_window_label(&self) -> String {
+        match TIME_WINDOWS[self.time_window] {
+            Some(window) => format!("last {}m", window.as_secs() / 60),
+            None => "all".to_string(),
+        }
+    }
+
+    fn toggle_log_scale(&mut self) {
+        self.log_scale = !self.log_scale;
+        self.locked_y_bounds.clear();
+    }
+
+    fn toggle_scope(&mut self) {
+        self.show_scope = !self.show_scope;
+    }
+
+    fn highlighted_metric(&self) -> Option<&String> {
+        self.list_state
+            .selected()
+            .and_then(|index| self.listed_metrics().get(index).copied())
+    }
+
+    /// Copies the graphed metric's name, or the highlighted one's when none
+    /// is graphed, to the system clipboard.
+    fn copy_metric_name(&mut self) {
+        let Some(metric) = self.selected_metric.clone().or_else(|| self.highlighted_metric().cloned()) else {
+            self.flash("No metric to copy");
+            return;
+        };
+        match self.clipboard.copy(&metric) {
+            Ok(()) => self.flash(format!("Copied {} to the clipboard", metric)),
+            Err(e) => self.flash(format!("Could not copy {}: {}", metric, e)),
+        }
+    }
+
+    fn toggle_detail(&mut self) {
+        self.show_detail = !self.show_detail;
+    }
+
+    /// Popup with everything known about the highlighted metric.
+    fn render_detail(&self, area: Rect, frame: &mut Frame) {
+        let Some(metric_name) = self.highlighted_metric() else {
+            return;
+        };
+        let info = self.metric_info.get(metric_name).cloned().unwrap_or_default();
+        let or_dash = |s: &str| if s.is_empty() { "-".to_string() } else { s.to_string() };
+        let points = self.metric_data.get(metric_name).map_or(0, VecDeque::len);
+        let attribute_sets = self.metric_attributes.get(metric_name);
+
+        let mut lines = vec![
+            Line::from(format!("Name:        {}", metric_name)),
+            Line::from(format!("Alias:       {}", self.aliases.get(metric_name).map_or("-", String::as_str))),
+            Line::from(format!("Description: {}", or_dash(&info.description))),
+            Line::from(format!("Unit:        {}", or_dash(&info.unit))),
+            Line::from(format!("Scope:       {}", info.scope.as_deref().unwrap_or("-"))),
+            Line::from(format!("Type:        {}", info.kind.map_or("-", |k| k.label()))),
+            Line::from(format!("Temporality: {}", info.temporality.map_or("-", |t| t.label()))),
+            Line::from(format!(
+                "Monotonic:   {}",
+                match info.monotonic {
+                    Some(true) => "yes (counter)",
+                    Some(false) => "no (up-down counter)",
+                    None => "-",
+                }
+            )),
+            Line::from(format!(
+                "Graphed as:  {}",
+                if self.accumulates(metric_name) {
+                    "running total"
+                } else if self.shows_rate(metric_name) {
+                    "per-second rate"
                 } else {
-                    self.selected_metric = Some(metric.clone());
-                    self.show_graph = true;
-                    self.recent_updates.clear();
+                    "raw values"
                 }
+            )),
+            Line::from(format!("Points:      {}", points)),
+            Line::from(format!(
+                "Received on: {}",
+                self.metric_listeners.get(metric_name).map_or("-".to_string(), |listeners| {
+                    listeners.iter().map(SocketAddr::to_string).collect::<Vec<_>>().join(", ")
+                })
+            )),
+            Line::from(format!("Attribute sets ({}):", attribute_sets.map_or(0, BTreeSet::len))),
+        ];
+        for attributes in attribute_sets.into_iter().flatten() {
+            let shown = if attributes.is_empty() { "(no attributes)" } else { attributes };
+            lines.push(Line::from(format!("  {}", shown)));
+        }
+
+        let popup = Paragraph::new(lines).block(
+            Block::default()
+                .title("Metric Details [d to close]")
+                .borders(Borders::ALL),
+        );
+        let area = centered_rect(70, 60, area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+
+    fn toggle_cardinality(&mut self) {
+        self.show_cardinality = !self.show_cardinality;
+    }
+
+    /// Popup ranking metrics by their number of distinct attribute sets, with
+    /// those above `--cardinality-warn` in red.
+    fn render_cardinality(&self, area: Rect, frame: &mut Frame) {
+        let limit = self.cardinality_warn;
+        let mut lines = vec![Line::from(match limit {
+            Some(limit) => format!("Warning above {} attribute sets per metric", limit),
+            None => "No warning threshold (set one with --cardinality-warn)".to_string(),
+        })];
+        lines.push(Line::from(""));
+        for (name, count) in self.cardinalities().into_iter().take(MAX_CARDINALITY_ROWS) {
+            let style = if limit.is_some_and(|limit| count > limit) {
+                Style::default().fg(Color::Red)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::styled(format!("{:>7}  {}", count, self.display_name(name)), style));
+        }
+
+        let popup = Paragraph::new(lines).block(
+            Block::default()
+                .title("Attribute Set Cardinality [C to close]")
+                .borders(Borders::ALL),
+        );
+        let area = centered_rect(70, 60, area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+
+    fn cycle_chart_style(&mut self) {
+        self.chart_style = self.chart_style.next();
+    }
+
+    fn toggle_heatmap(&mut self) {
+        let is_histogram = self
+            .selected_metric
+            .as_ref()
+            .is_some_and(|name| self.histogram_columns.contains_key(name));
+        if !self.show_heatmap && !is_histogram {
+            self.flash("The heatmap needs a selected histogram metric");
+            return;
+        }
+        self.show_heatmap = !self.show_heatmap;
+    }
+
+    /// The heatmap columns, oldest first: those sharing the latest layout, with
+    /// cumulative counts turned into the increase since the previous export
+    /// (or left as they are after a reset).
+    fn heatmap_columns(columns: &VecDeque<BucketColumn>) -> Vec<BucketColumn> {
+        let Some(latest) = columns.back() else {
+            return Vec::new();
+        };
+        let same_layout: Vec<&BucketColumn> = columns.iter().filter(|c| c.bounds == latest.bounds).collect();
+        let mut shown = Vec::with_capacity(same_layout.len());
+        for (i, &column) in same_layout.iter().enumerate() {
+            if !column.cumulative {
+                shown.push(column.clone());
+                continue;
+            }
+            match i.checked_sub(1).map(|prev| &same_layout[prev].counts) {
+                Some(prev) if column.counts.iter().zip(prev).all(|(c, p)| c >= p) => shown.push(BucketColumn {
+                    counts: column.counts.iter().zip(prev).map(|(c, p)| c - p).collect(),
+                    ..column.clone()
+                }),
+                Some(_) => shown.push(column.clone()),
+                // The first cumulative column is everything since the start
+                // time and would drown out the rest, unless it's all there is.
+                None if same_layout.len() == 1 => shown.push(column.clone()),
+                None => {}
             }
         }
+        shown
+    }
+
+    /// One column per histogram export and one row per bucket (merged when
+    /// there are more buckets than rows), colored by count.
+    fn render_heatmap(&self, metric_name: &str, columns: &VecDeque<BucketColumn>, area: Rect, frame: &mut Frame) {
+        let since = self.window_start();
+        let mut cells = Self::heatmap_columns(columns);
+        cells.retain(|column| column.timestamp >= since);
+        let Some(bounds) = cells.last().map(|column| column.bounds.clone()) else {
+            return Self::render_waiting(metric_name, area, frame);
+        };
+        let unit = self.metric_info.get(metric_name).map_or("", |info| info.unit.as_str());
+
+        let rows = usize::from(area.height.saturating_sub(3)).max(1);
+        let buckets = bounds.len() + 1;
+        let per_row = buckets.div_ceil(rows);
+        // Rows top to bottom, from the highest bucket group down, labelled
+        // with the group's upper bound.
+        let groups: Vec<(String, std::ops::Range<usize>)> = (0..buckets)
+            .step_by(per_row)
+            .map(|start| {
+                let end = (start + per_row).min(buckets);
+                let label = match bounds.get(end - 1) {
+                    Some(bound) => format!("≤{}", format_value(*bound, unit, self.precision)),
+                    None => format!(
+                        ">{}",
+                        bounds.last().map_or("-inf".to_string(), |b| format_value(*b, unit, self.precision))
+                    ),
+                };
+                (label, start..end)
+            })
+            .rev()
+            .collect();
+        let label_width = groups.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+
+        let plot_width = usize::from(area.width.saturating_sub(3)).saturating_sub(label_width).max(1);
+        let shown = &cells[cells.len().saturating_sub(plot_width)..];
+        let cell_width = (plot_width / shown.len().max(1)).clamp(1, 3);
+        let max = shown
+            .iter()
+            .flat_map(|column| groups.iter().map(|(_, range)| column.counts[range.clone()].iter().sum::<u64>()))
+            .max()
+            .unwrap_or(0);
+
+        let mut lines: Vec<Line> = groups
+            .iter()
+            .map(|(label, range)| {
+                let mut spans = vec![Span::raw(format!("{:>width$} ", label, width = label_width))];
+                spans.extend(shown.iter().map(|column| {
+                    let count: u64 = column.counts[range.clone()].iter().sum();
+                    if count == 0 {
+                        return Span::raw(" ".repeat(cell_width));
+                    }
+                    let level = (count as f64 / max as f64 * (HEATMAP_COLORS.len() - 1) as f64).round() as usize;
+                    Span::styled("█".repeat(cell_width), Style::default().fg(HEATMAP_COLORS[level]))
+                }));
+                Line::from(spans)
+            })
+            .collect();
+        if let (Some(first), Some(last)) = (shown.first(), shown.last()) {
+            let first = self.timezone.format(first.timestamp as i64, "%H:%M:%S").unwrap_or_default();
+            let last = self.timezone.format(last.timestamp as i64, "%H:%M:%S").unwrap_or_default();
+            let gap = (shown.len() * cell_width).saturating_sub(first.len() + last.len()).max(1);
+            lines.push(Line::from(format!("{:width$} {}{}{}", "", first, " ".repeat(gap), last, width = label_width)));
+        }
+
+        let counted = if columns.back().is_some_and(|c| c.cumulative) {
+            "increase per export"
+        } else {
+            "count per export"
+        };
+        let title = format!(
+            "Heatmap: {} ({} exports, {}, max {}, {}) [h: graph, [/]: window]",
+            metric_name,
+            shown.len(),
+            counted,
+            max,
+            self.time_window_label()
+        );
+        let heatmap = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+        frame.render_widget(heatmap, area);
     }
 
     fn render_graph(&self, metric_name: &String, area: Rect, frame: &mut Frame) {
-        if let Some(points) = self.metric_data.get(metric_name) {
-            let data: Vec<(f64, f64)> = points
+        if self.show_heatmap {
+            if let Some(columns) = self.histogram_columns.get(metric_name) {
+                return self.render_heatmap(metric_name, columns, area, frame);
+            }
+        }
+        let mut series = self.graph_series(metric_name);
+        // Cut the series to the zoomed and panned view before downsampling, so
+        // zooming in shows more detail rather than fewer points.
+        if self.zoom > 0 || self.view_end.is_some() {
+            if let Some((min_x, max_x)) = self.x_extent(metric_name) {
+                let [start, end] = self.x_view(min_x, max_x);
+                for s in &mut series {
+                    s.data.retain(|p| p.0 >= start && p.0 <= end);
+                }
+            }
+        }
+        // Braille markers give two dots per cell, so more points than that can't be told apart.
+        let max_rendered_points = usize::from(area.width.saturating_sub(2)) * 2;
+        for s in &mut series {
+            s.downsample(max_rendered_points);
+        }
+        let threshold = self.threshold_y(metric_name);
+        // Overlays come last and have thresholds of their own scale, so only
+        // the metric's own series are checked against it.
+        let overlays = self
+            .overlay_metrics
+            .iter()
+            .filter(|m| *m != metric_name && self.metric_data.contains_key(*m))
+            .count();
+        let own_series = series.len().saturating_sub(overlays);
+        let above: Vec<(f64, f64)> = threshold.map_or_else(Vec::new, |threshold| {
+            series[..own_series]
                 .iter()
-                .map(|point| (point.timestamp as f64, point.value))
-                .collect();
+                .flat_map(|s| s.data.iter().copied())
+                .filter(|p| p.0.is_finite() && p.1 > threshold)
+                .collect()
+        });
+        let all_points = || {
+            series
+                .iter()
+                .flat_map(|s| s.data.iter())
+                .filter(|p| p.0.is_finite() && p.1.is_finite())
+        };
 
-            if !data.is_empty() {
-                let min_x = data.first().map(|p| p.0).unwrap_or(0.0);
-                let max_x = data.last().map(|p| p.0).unwrap_or(0.0);
-                let min_y = data.iter().map(|p| p.1).reduce(f64::min).unwrap_or(0.0);
-                let max_y = data.iter().map(|p| p.1).reduce(f64::max).unwrap_or(0.0);
-
-                // Create labels for Y axis
-                let y_labels = vec![
-                    format!("{:.2}", min_y),
-                    format!("{:.2}", (min_y + max_y) / 2.0),
-                    format!("{:.2}", max_y),
-                ]
+        if all_points().next().is_some() {
+            let [min_x, max_x] = padded_bounds(
+                all_points().map(|p| p.0).reduce(f64::min).unwrap_or(0.0),
+                all_points().map(|p| p.0).reduce(f64::max).unwrap_or(0.0),
+            );
+            let locked_y = self.locked_y_bounds.get(metric_name).copied();
+            // The threshold counts towards the auto-fitted range so its line is always in view.
+            let [min_y, max_y] = locked_y.unwrap_or_else(|| {
+                padded_bounds(
+                    all_points().map(|p| p.1).chain(threshold).reduce(f64::min).unwrap_or(0.0),
+                    all_points().map(|p| p.1).chain(threshold).reduce(f64::max).unwrap_or(0.0),
+                )
+            });
+
+            if self.chart_style == ChartStyle::Bar {
+                for s in &mut series {
+                    s.data = ChartStyle::bar_stems(&s.data, min_y);
+                }
+            }
+            let graph_type = match self.chart_style {
+                ChartStyle::Scatter => ratatui::widgets::GraphType::Scatter,
+                ChartStyle::Line | ChartStyle::Bar => ratatui::widgets::GraphType::Line,
+            };
+
+            // Create labels for Y axis
+            let y_label = |y: f64| {
+                if self.log_scale && !self.normalize {
+                    format!("1e{:.1}", y)
+                } else {
+                    format_decimal(y, self.decimals())
+                }
+            };
+            let y_labels = [min_y, (min_y + max_y) / 2.0, max_y]
                 .into_iter()
-                .map(|s| Span::raw(s))
+                .map(|y| Span::raw(y_label(y)))
                 .collect::<Vec<Span>>();
 
-                // Create labels for X axis with formatted timestamps
-                let x_labels = vec![min_x, (min_x + max_x) / 2.0, max_x]
-                    .into_iter()
-                    .map(|ts| {
-                        let datetime = NaiveDateTime::from_timestamp(ts as i64, 0);
-                        let formatted_time = format!("{:02}:{:02}:{:02}", datetime.hour(), datetime.minute(), datetime.second());
-                        Span::raw(formatted_time)
-                    })
-                    .collect::<Vec<Span>>();
-
-                let dataset = Dataset::default()
-                    .name(metric_name.clone())
-                    .marker(symbols::Marker::Braille)
-                    .graph_type(ratatui::widgets::GraphType::Line)
-                    .data(&data);
-
-                let chart = Chart::new(vec![dataset])
-                    .block(
-                        Block::default()
-                            .title(format!("Metric: {}", metric_name))
-                            .borders(Borders::ALL),
-                    )
-                    .x_axis(
-                        Axis::default()
-                            .title("Time (hh:mm:ss)")
-                            .bounds([min_x, max_x])
-                            .labels(x_labels),
-                    )
-                    .y_axis(
-                        Axis::default()
-                            .title("Value")
-                            .bounds([min_y, max_y])
-                            .labels(y_labels),
-                    );
+            // Create labels for X axis with formatted timestamps
+            let label_count = x_label_count(area.width);
+            let x_labels = (0..label_count)
+                .map(|i| min_x + (max_x - min_x) * i as f64 / (label_count - 1) as f64)
+                .map(|ts| Span::raw(self.timezone.format(ts as i64, "%H:%M:%S").unwrap_or_default()))
+                .collect::<Vec<Span>>();
 
-                frame.render_widget(chart, area);
+            // Only the first few series get a legend entry; the rest are summarized
+            // by an empty, named dataset so the legend can't swallow the plot.
+            // Each gap-separated segment is its own dataset so no line crosses a gap.
+            let bridge_repeats = self.dedup_epsilon.is_some();
+            let mut datasets: Vec<Dataset> = series
+                .iter()
+                .enumerate()
+                .flat_map(|(i, s)| {
+                    s.segments(bridge_repeats).into_iter().enumerate().map(move |(j, segment)| {
+                        let dataset = Dataset::default()
+                            .marker(symbols::Marker::Braille)
+                            .graph_type(graph_type)
+                            .style(Style::default().fg(s.color))
+                            .data(segment);
+                        if i < MAX_LEGEND_ENTRIES && j == 0 {
+                            dataset.name(s.name.clone())
+                        } else {
+                            dataset
+                        }
+                    })
+                })
+                .collect();
+            if series.len() > MAX_LEGEND_ENTRIES {
+                datasets.push(
+                    Dataset::default()
+                        .name(format!("+{} more", series.len() - MAX_LEGEND_ENTRIES))
+                        .style(Style::default().fg(Color::DarkGray)),
+                );
             }
+            // A dot per cell across the plot reads as a dashed line.
+            let threshold_line: Vec<(f64, f64)> = threshold.map_or_else(Vec::new, |threshold| {
+                let steps = usize::from(area.width.saturating_sub(2)).max(2);
+                (0..=steps)
+                    .map(|i| (min_x + (max_x - min_x) * i as f64 / steps as f64, threshold))
+                    .collect()
+            });
+            if let Some(value) = self.thresholds.get(metric_name).filter(|_| threshold.is_some()) {
+                datasets.push(
+                    Dataset::default()
+                        .name(format!("threshold {}", format_decimal(*value, self.decimals())))
+                        .marker(symbols::Marker::Braille)
+                        .graph_type(ratatui::widgets::GraphType::Scatter)
+                        .style(Style::default().fg(Color::Gray))
+                        .data(&threshold_line),
+                );
+                datasets.push(
+                    Dataset::default()
+                        .marker(symbols::Marker::Braille)
+                        .graph_type(ratatui::widgets::GraphType::Scatter)
+                        .style(Style::default().fg(Color::Red))
+                        .data(&above),
+                );
+            }
+
+            let lock = if locked_y.is_some() { ", y locked" } else { "" };
+            let rate = if self.accumulates(metric_name) {
+                ", running total"
+            } else if self.shows_rate(metric_name) {
+                ", per second"
+            } else {
+                ""
+            };
+            let view = self.view_label();
+            let title = if series.len() > 1 {
+                format!(
+                    "Metric: {} ({}, {}{}{}{}) [t: style, p: quantiles, [/]: window, z/Z: zoom, ←/→: pan, F: follow, y: lock]",
+                    metric_name,
+                    self.chart_style.label(),
+                    self.time_window_label(),
+                    view,
+                    rate,
+                    lock
+                )
+            } else {
+                format!(
+                    "Metric: {} ({}, {}{}{}{}) [t: style, [/]: window, z/Z: zoom, ←/→: pan, F: follow, y: lock, R: rate]",
+                    metric_name,
+                    self.chart_style.label(),
+                    self.time_window_label(),
+                    view,
+                    rate,
+                    lock
+                )
+            };
+
+            let chart = Chart::new(datasets)
+                .legend_position(Some(LegendPosition::TopLeft))
+                .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
+                .block(
+                    Block::default()
+                        .title(title)
+                        .borders(Borders::ALL),
+                )
+                .x_axis(
+                    Axis::default()
+                        .title(match self.timezone {
+                            Timezone::Local => "Time (hh:mm:ss)",
+                            Timezone::Utc => "Time (hh:mm:ss UTC)",
+                        })
+                        .bounds([min_x, max_x])
+                        .labels(x_labels),
+                )
+                .y_axis(
+                    Axis::default()
+                        .title(match (self.normalize, self.log_scale) {
+                            (true, true) => "log10(Value) (normalized)",
+                            (true, false) => "Value (normalized)",
+                            (false, true) => "Value (log10)",
+                            (false, false) => "Value",
+                        })
+                        .bounds([min_y, max_y])
+                        .labels(y_labels),
+                );
+
+            frame.render_widget(chart, area);
+        } else {
+            Self::render_waiting(metric_name, area, frame);
         }
     }
+
+    /// Shown instead of an empty chart until the metric has a finite point to plot.
+    fn render_waiting(metric_name: &str, area: Rect, frame: &mut Frame) {
+        let padding = usize::from(area.height.saturating_sub(3) / 2);
+        let mut lines = vec![Line::from(""); padding];
+        lines.push(Line::from("Waiting for data..."));
+        let placeholder = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::DarkGray))
+            .block(
+                Block::default()
+                    .title(format!("Metric: {}", metric_name))
+                    .borders(Borders::ALL),
+            );
+        frame.render_widget(placeholder, area);
+    }
 }
-pub async fn run_tui(mut rx: UnboundedReceiver<UiMessage>) -> Result<(), DashboardError> {
+
+/// Runs until `q` is pressed or `shutdown` fires (or its sender is dropped),
+/// restoring the terminal either way. Without a terminal on stdout, e.g. when
+/// it is redirected, prints the `--watch` table instead.
+pub async fn run_tui(
+    mut rx: UnboundedReceiver<UiMessage>,
+    control_tx: UnboundedSender<ControlMessage>,
+    mut shutdown: oneshot::Receiver<()>,
+    options: TuiOptions,
+) -> Result<(), DashboardError> {
+    if !io::stdout().is_terminal() {
+        eprintln!(
+            "stdout is not a terminal, so printing the latest values every {}s instead of the TUI (choose the interval with --watch)",
+            FALLBACK_WATCH_INTERVAL.as_secs()
+        );
+        return tokio::select! {
+            result = run_watch(rx, FALLBACK_WATCH_INTERVAL, options) => result,
+            _ = shutdown => Ok(()),
+        };
+    }
     enable_raw_mode()?;
+    TUI_ACTIVE.store(true, Ordering::SeqCst);
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut state = TuiState::new();
+    // A burst beyond the per-frame budget stays in the channel for later frames,
+    // so it animates in instead of landing in one jump.
+    let max_per_frame = options.max_per_frame.unwrap_or(usize::MAX);
+    // With `--realtime`, keys come from an async stream so the loop can also
+    // wake up for messages; otherwise it polls once per refresh interval.
+    let mut events = options.realtime.then(EventStream::new);
+    let mut rx_open = true;
+    let mut state = TuiState::new(options);
 
     loop {
-        while let Ok(message) = rx.try_recv() {
-            match message {
-                UiMessage::NewMetric(metric) => state.add_metric(metric),
-                UiMessage::MetricUpdate(update) => state.add_update(update),
-                UiMessage::MetricDataPoint { name, point } => state.add_metric_point(name, point),
-            }
+        if !matches!(shutdown.try_recv(), Err(oneshot::error::TryRecvError::Empty)) {
+            break;
         }
+        for message in std::iter::from_fn(|| rx.try_recv().ok()).take(max_per_frame) {
+            state.apply(message);
+        }
+        state.prune_stale();
 
         terminal.draw(|f| {
-            let chunks = Layout::default()
+            let outer = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+                .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
                 .split(f.size());
+            // Compact mode gives one pane the whole screen: the graph or updates
+            // while a metric is selected, the list otherwise.
+            let (list_area, pane_area) = if !state.compact {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(
+                        [Constraint::Percentage(state.split), Constraint::Percentage(100 - state.split)].as_ref(),
+                    )
+                    .split(outer[0]);
+                (Some(chunks[0]), Some(chunks[1]))
+            } else if state.selected_metric.is_some() {
+                (None, Some(outer[0]))
+            } else {
+                (Some(outer[0]), None)
+            };
 
-            let metrics: Vec<ListItem> = state
-                .discovered_metrics
+            let listed = state.listed_metrics();
+            let metrics: Vec<ListItem> = listed
                 .iter()
-                .map(|m| {
-                    let style = if Some(m) == state.selected_metric.as_ref() {
+                .map(|&m| {
+                    let style = if state.alerting.contains(m) {
+                        Style::default().fg(Color::Red)
+                    } else if Some(m) == state.selected_metric.as_ref() {
                         Style::default().fg(Color::Yellow)
+                    } else if state.is_going_stale(m) {
+                        Style::default().fg(Color::DarkGray)
                     } else {
                         Style::default()
                     };
-                    ListItem::new(m.as_str()).style(style)
+                    let line = Line::from(vec![
+                        Span::raw(state.display_name(m).to_string()),
+                        Span::styled(
+                            if state.overlay_metrics.contains(m) { " +" } else { "" },
+                            Style::default().fg(Color::Cyan),
+                        ),
+                        Span::raw(" "),
+                        Span::styled(state.scope_label(m), Style::default().fg(Color::Blue)),
+                        Span::styled(state.temporality_label(m), Style::default().fg(Color::Magenta)),
+                        Span::styled(state.point_count_label(m), Style::default().fg(Color::DarkGray)),
+                        Span::styled(state.cardinality_label(m), Style::default().fg(Color::Red)),
+                        Span::raw(" "),
+                        Span::styled(state.sparkline(m), Style::default().fg(Color::Cyan)),
+                    ]);
+                    ListItem::new(line).style(style)
                 })
                 .collect();
 
+            let kind_filter = match state.kind_filter {
+                KindFilter::All => String::new(),
+                filter => format!(" ({}: {} of {})", filter.label(), listed.len(), state.discovered_metrics.len()),
+            };
+            let kind_filter = match state.sort_mode {
+                SortMode::Name => kind_filter,
+                SortMode::Arrival => format!("{} (arrival order)", kind_filter),
+            };
             let title = if state.selected_metric.is_some() {
-                "Discovered Metrics [j/k to navigate, Enter to unfilter]"
+                format!("Discovered Metrics{} [j/k to navigate, Enter to unfilter, f: type, /: search]", kind_filter)
             } else {
-                "Discovered Metrics [j/k to navigate, Enter to filter]"
+                format!("Discovered Metrics{} [j/k to navigate, Enter to filter, f: type, /: search]", kind_filter)
             };
 
             let metrics_list = List::new(metrics)
-                .block(Block::default().title(title).borders(Borders::ALL))
+                .block(Block::default().title(title.clone()).borders(Borders::ALL))
                 .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
-            f.render_stateful_widget(metrics_list, chunks[0], &mut state.list_state);
-
-            if state.show_graph {
-                if let Some(metric_name) = &state.selected_metric {
-                    state.render_graph(metric_name, chunks[1], f);
+            if let Some(area) = list_area {
+                if state.discovered_metrics.is_empty() {
+                    // The startup log line is hidden by the alternate screen,
+                    // so the empty list says where to send data instead.
+                    let hint = Paragraph::new(vec![
+                        Line::from(format!("Listening on {}", state.listening)),
+                        Line::from("Point your exporter here; metrics show up as soon as they arrive."),
+                    ])
+                    .style(Style::default().fg(Color::DarkGray))
+                    .block(Block::default().title(title).borders(Borders::ALL));
+                    f.render_widget(hint, area);
+                } else {
+                    f.render_stateful_widget(metrics_list, area, &mut state.list_state);
                 }
-            } else {
-                let updates_title = if let Some(metric) = &state.selected_metric {
-                    format!("Recent Updates (Filtered: {})", metric)
+            }
+
+            if let Some(area) = pane_area {
+                if state.show_graph {
+                    if let Some(metric_name) = &state.selected_metric {
+                        state.render_graph(metric_name, area, f);
+                    }
                 } else {
-                    "Recent Updates (All Metrics)".to_string()
-                };
+                    state.render_updates(area, f);
+                }
+            }
+
+            let status = match &state.command_line {
+                Some(line) => Paragraph::new(format!(":{}", line)),
+                None => Paragraph::new(state.status_line())
+                    .style(Style::default().bg(Color::DarkGray).fg(Color::White)),
+            };
+            f.render_widget(status, outer[1]);
 
-                let updates: Vec<ListItem> = state
-                    .recent_updates
-                    .iter()
-                    .map(|u| ListItem::new(u.as_str()))
-                    .collect();
-                let updates_list = List::new(updates)
-                    .block(Block::default().title(updates_title).borders(Borders::ALL));
-                f.render_widget(updates_list, chunks[1]);
+            if state.show_detail {
+                state.render_detail(f.size(), f);
+            }
+            if state.show_cardinality {
+                state.render_cardinality(f.size(), f);
+            }
+            if let Some(finder) = &state.finder {
+                state.render_finder(finder, f.size(), f);
             }
         })?;
 
-        if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
+        let last_draw = Instant::now();
+
+        let event = match events.as_mut() {
+            Some(events) => tokio::select! {
+                event = events.next() => match event {
+                    Some(event) => Some(event?),
+                    None => break,
+                },
+                message = rx.recv(), if rx_open => {
+                    match message {
+                        Some(message) => state.apply(message),
+                        None => rx_open = false,
+                    }
+                    // Let the rest of a burst queue up before the next frame.
+                    tokio::time::sleep(REALTIME_FRAME.saturating_sub(last_draw.elapsed())).await;
+                    None
+                }
+                _ = tokio::time::sleep(state.refresh_interval) => None,
+                _ = &mut shutdown => break,
+            },
+            None if event::poll(state.refresh_interval)? => Some(event::read()?),
+            None => None,
+        };
+        if let Some(event) = event {
+            if let Event::Resize(width, height) = event {
+                // Redraw right away from a cleared buffer, so labels laid out for
+                // the old size don't linger or overlap.
+                terminal.resize(Rect::new(0, 0, width, height))?;
+                continue;
+            }
+            if let Event::Key(key) = event {
+                if state.handle_finder_key(key.code)
+                    || state.handle_command_key(key.code)
+                    || state.handle_jump_key(key.code)
+                {
+                    continue;
+                }
                 match key.code {
                     KeyCode::Char('q') => break,
                     KeyCode::Char('j') => state.next(),
                     KeyCode::Char('k') => state.previous(),
+                    KeyCode::Char('G') => state.select_last(),
                     KeyCode::Enter => state.toggle_selected_metric(),
+                    KeyCode::Char('u') => state.toggle_view(),
+                    KeyCode::Char('p') => state.toggle_quantiles(),
+                    KeyCode::Char('t') => state.cycle_chart_style(),
+                    KeyCode::Char('h') => state.toggle_heatmap(),
+                    KeyCode::Char('f') => state.cycle_kind_filter(),
+                    KeyCode::Char('S') => state.cycle_sort_mode(),
+                    KeyCode::Char('w') => state.toggle_wrap_updates(),
+                    KeyCode::Char('R') => state.toggle_rate(),
+                    KeyCode::Char('a') => state.toggle_accumulate(),
+                    KeyCode::Char('d') => state.toggle_detail(),
+                    KeyCode::Char('C') => state.toggle_cardinality(),
+                    KeyCode::Char('o') => state.toggle_overlay(),
+                    KeyCode::Char('n') => state.toggle_normalize(),
+                    KeyCode::Char('l') => state.toggle_log_scale(),
+                    KeyCode::Char('y') => state.toggle_y_lock(),
+                    KeyCode::Char('Y') => state.copy_metric_name(),
+                    KeyCode::Char('[') => state.adjust_time_window(false),
+                    KeyCode::Char(']') => state.adjust_time_window(true),
+                    KeyCode::Char('z') => state.adjust_zoom(true),
+                    KeyCode::Char('Z') => state.adjust_zoom(false),
+                    KeyCode::Left => state.pan(false),
+                    KeyCode::Right => state.pan(true),
+                    KeyCode::Char('F') => state.follow_live(),
+                    KeyCode::Char('v') => state.toggle_compact(),
+                    KeyCode::Char('s') => state.toggle_scope(),
+                    KeyCode::Char('e') => state.export_selected_csv(),
+                    KeyCode::Char('r') => state.export_markdown_report(),
+                    KeyCode::Char('<') => state.adjust_split(false),
+                    KeyCode::Char('>') => state.adjust_split(true),
+                    KeyCode::Char('+') => state.adjust_refresh_interval(true),
+                    KeyCode::Char('-') => state.adjust_refresh_interval(false),
+                    KeyCode::Char('c') => {
+                        state.clear_data();
+                        if control_tx.send(ControlMessage::ClearSeenMetrics).is_err() {
+                            state.flash("Cleared the TUI, but the receiver is gone");
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -272,6 +2765,216 @@ pub async fn run_tui(mut rx: UnboundedReceiver<UiMessage>) -> Result<(), Dashboa
         DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+    TUI_ACTIVE.store(false, Ordering::SeqCst);
+
+    if state.restore_session {
+        if let Err(e) = session::save(&state.session()) {
+            tracing::warn!("Failed to save the session: {}", e);
+        }
+    }
 
     Ok(())
 }
+
+/// Leaves raw mode and the alternate screen if the TUI holds them; called by
+/// the panic hook, so errors are ignored and it is a no-op in headless modes.
+pub fn restore_terminal() {
+    if TUI_ACTIVE.swap(false, Ordering::SeqCst) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, crossterm::cursor::Show);
+    }
+}
+
+/// Headless alternative to the TUI: prints a table of the latest values every
+/// `interval`. The screen is only cleared between frames when stdout is a terminal,
+/// so the output can be piped to `tee` or a log file.
+pub async fn run_watch(
+    mut rx: UnboundedReceiver<UiMessage>,
+    interval: Duration,
+    options: TuiOptions,
+) -> Result<(), DashboardError> {
+    let clear = io::stdout().is_terminal();
+    let mut state = TuiState::new(options);
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        while let Ok(message) = rx.try_recv() {
+            state.apply(message);
+        }
+        state.prune_stale();
+
+        let mut frame = String::new();
+        if clear {
+            frame.push_str("\x1b[2J\x1b[H");
+        }
+        frame.push_str(&format!(
+            "{} | Metrics: {} | RPS: {}\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            state.discovered_metrics.len(),
+            state.stats.requests_per_second,
+        ));
+        frame.push_str(&state.summary_table());
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(frame.as_bytes())?;
+        stdout.write_all(b"\n")?;
+        stdout.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_limit_falls_back_to_the_base_metric() {
+        let history_for = HashMap::from([("latency".to_string(), 50), ("latency[p50]".to_string(), 20)]);
+        assert_eq!(history_limit(&history_for, 1000, "latency"), 50);
+        assert_eq!(history_limit(&history_for, 1000, "latency[p99]"), 50);
+        assert_eq!(history_limit(&history_for, 1000, "latency[p50]"), 20);
+        assert_eq!(history_limit(&history_for, 1000, "other[p99]"), 1000);
+        assert_eq!(history_limit(&history_for, 1000, "latency.p99"), 1000);
+    }
+
+    fn series(data: &[(f64, f64)]) -> Vec<GraphSeries> {
+        vec![GraphSeries {
+            name: "test".to_string(),
+            color: Color::Reset,
+            data: data.to_vec(),
+        }]
+    }
+
+    #[test]
+    fn single_point_bounds_are_padded_around_it() {
+        assert_eq!(TuiState::fitted_y_bounds(&series(&[(1.0, 50.0)])), Some([45.0, 55.0]));
+        assert_eq!(TuiState::fitted_y_bounds(&series(&[(1.0, 0.0)])), Some([-1.0, 1.0]));
+        assert_eq!(TuiState::fitted_y_bounds(&series(&[])), None);
+    }
+
+    #[test]
+    fn bounds_ignore_nan_and_infinite_values() {
+        assert_eq!(TuiState::fitted_y_bounds(&series(&[(1.0, f64::NAN), (2.0, f64::NAN)])), None);
+        let mixed = series(&[(1.0, f64::NEG_INFINITY), (2.0, 2.0), (3.0, f64::INFINITY), (4.0, 8.0), (5.0, f64::NAN)]);
+        assert_eq!(TuiState::fitted_y_bounds(&mixed), Some([2.0, 8.0]));
+        assert_eq!(TuiState::fitted_y_bounds(&series(&[(1.0, f64::INFINITY), (2.0, f64::NEG_INFINITY)])), None);
+    }
+
+    #[test]
+    fn centered_rect_stays_centered_inside_the_area() {
+        assert_eq!(centered_rect(50, 50, Rect::new(0, 0, 100, 40)), Rect::new(25, 10, 50, 20));
+        // Odd leftovers round towards the top left, and the offset is kept.
+        assert_eq!(centered_rect(60, 50, Rect::new(10, 5, 25, 9)), Rect::new(15, 7, 15, 4));
+        // Over 100% is capped to the area, and a zero-sized area stays empty.
+        assert_eq!(centered_rect(150, 100, Rect::new(3, 4, 20, 10)), Rect::new(3, 4, 20, 10));
+        assert_eq!(centered_rect(50, 50, Rect::new(0, 0, 0, 0)), Rect::new(0, 0, 0, 0));
+    }
+
+    #[test]
+    fn pruned_metric_keeps_its_info_when_it_reports_again() {
+        let mut state = TuiState::new(TuiOptions {
+            stale_timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        let info = MetricInfo {
+            kind: Some(MetricKind::Sum),
+            unit: "By".to_string(),
+            ..Default::default()
+        };
+        state.apply(UiMessage::NewMetric("bytes".to_string()));
+        state.apply(UiMessage::MetricInfo { name: "bytes".to_string(), info });
+        let old = MetricPoint { timestamp: 1, value: 1.0 };
+        state.apply(UiMessage::MetricDataPointBatch(vec![("bytes".to_string(), old)]));
+
+        state.prune_stale();
+        assert!(!state.discovered_metrics.contains(&"bytes".to_string()));
+        state.apply(UiMessage::MetricReappeared { name: "bytes".to_string(), gap: Duration::from_secs(90) });
+        assert!(!state.metric_updates.contains_key("bytes"));
+        assert!(!state.metric_update_times.contains_key("bytes"));
+        assert!(state.recent_updates.front().is_some_and(|update| update.contains("REAPPEARED")));
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let fresh = MetricPoint { timestamp: now, value: 2.0 };
+        state.apply(UiMessage::MetricDataPointBatch(vec![("bytes".to_string(), fresh)]));
+        assert!(state.discovered_metrics.contains(&"bytes".to_string()));
+        let info = state.metric_info.get("bytes").unwrap();
+        assert_eq!(info.kind, Some(MetricKind::Sum));
+        assert_eq!(info.unit, "By");
+    }
+
+    #[test]
+    fn replayed_points_are_aged_from_the_end_of_the_recording() {
+        let mut state = TuiState::new(TuiOptions {
+            stale_timeout: Some(Duration::from_secs(60)),
+            replay_end: Some(1_000_000),
+            ..Default::default()
+        });
+        let points = vec![
+            ("kept".to_string(), MetricPoint { timestamp: 999_990, value: 1.0 }),
+            ("ended".to_string(), MetricPoint { timestamp: 900_000, value: 1.0 }),
+        ];
+        state.apply(UiMessage::NewMetric("kept".to_string()));
+        state.apply(UiMessage::NewMetric("ended".to_string()));
+        state.apply(UiMessage::MetricDataPointBatch(points));
+
+        assert_eq!(state.age_secs("kept"), Some(10));
+        state.prune_stale();
+        assert_eq!(state.discovered_metrics, vec!["kept".to_string()]);
+        state.time_window = TIME_WINDOWS.iter().position(|w| w.is_some()).unwrap();
+        assert_eq!(state.window_start(), 1_000_000 - TIME_WINDOWS[state.time_window].unwrap().as_secs());
+    }
+
+    #[test]
+    fn pruned_quantile_series_are_not_listed_when_they_report_again() {
+        let mut state = TuiState::new(TuiOptions {
+            stale_timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        let names = ["latency", "latency[p50]", "latency[p99]"];
+        let batch = |timestamp: u64| {
+            let points = names.iter().map(|name| (name.to_string(), MetricPoint { timestamp, value: 1.0 }));
+            UiMessage::MetricDataPointBatch(points.collect())
+        };
+        state.apply(UiMessage::NewMetric("latency".to_string()));
+        state.apply(batch(1));
+
+        state.prune_stale();
+        assert!(state.discovered_metrics.is_empty());
+        assert!(state.metric_data.is_empty());
+
+        state.apply(batch(chrono::Utc::now().timestamp() as u64));
+        assert_eq!(state.discovered_metrics, vec!["latency".to_string()]);
+        assert!(state.metric_data.contains_key("latency[p99]"));
+    }
+
+    #[test]
+    fn session_brings_back_sort_order_and_theme() {
+        let saved = Session {
+            sort_mode: Some(SortMode::Arrival),
+            theme: Some(ThemeChoice::Light),
+            ..Default::default()
+        };
+        let saved: Session = toml::from_str(&toml::to_string(&saved).unwrap()).unwrap();
+        let mut state = TuiState::new(TuiOptions {
+            session: Some(saved.clone()),
+            ..Default::default()
+        });
+        assert_eq!(state.sort_mode, SortMode::Arrival);
+        assert_eq!(state.theme_choice, ThemeChoice::Light);
+
+        state.cycle_sort_mode();
+        state.run_command(Command::Theme("dark".to_string()));
+        let session = state.session();
+        assert_eq!(session.sort_mode, Some(SortMode::Name));
+        assert_eq!(session.theme, Some(ThemeChoice::Dark));
+
+        // Flags still win over the saved view.
+        let state = TuiState::new(TuiOptions {
+            theme: Some(ThemeChoice::Dark),
+            session: Some(saved),
+            ..Default::default()
+        });
+        assert_eq!(state.sort_mode, SortMode::Arrival);
+        assert_eq!(state.theme_choice, ThemeChoice::Dark);
+    }
+}